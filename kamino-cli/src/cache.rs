@@ -0,0 +1,309 @@
+use crate::{
+    findings::RepoReport,
+    fixture::{self, FixtureError},
+};
+use git2::Repository;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+/// Name of the file kept in the scanned directory with the results of each repo, for `--cache-ttl`.
+pub const CACHE_FILENAME: &str = ".kaminocache";
+
+/// Error type for [`Cache::load()`].
+#[derive(thiserror::Error, Debug)]
+pub enum CacheError {
+    #[error("failed to read cache")]
+    Io(#[from] io::Error),
+    #[error("line {line}: {message}")]
+    Parse { line: usize, message: String },
+}
+
+// A repo's report, with when it was cached and the key it is only good for.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Entry {
+    saved: i64,
+    key: String,
+    report: RepoReport,
+}
+
+/// The results of checking each repo, so that repos which haven't changed since can be skipped.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Cache {
+    entries: BTreeMap<PathBuf, Entry>,
+}
+
+impl Cache {
+    /// Read the cache saved by [`Cache::save()`], or an empty one if there isn't one yet.
+    ///
+    /// Each repo starts with a line with tab-separated fields: `cache`, the time it was cached in seconds since the
+    /// Unix epoch and its key. Its report follows, as [`fixture::save()`] records it.
+    ///
+    /// # Errors
+    ///
+    /// See [`CacheError`].
+    pub fn load(path: &Path) -> Result<Self, CacheError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries = BTreeMap::new();
+        // The line each entry starts on, and the records of its report
+        let mut entry: Option<(usize, i64, String, String)> = None;
+        let lines = text.lines().map(Some).chain([None]);
+        for (i, line) in lines.enumerate() {
+            let line_number = i + 1;
+            let starts_entry = match line {
+                Some(line) => line.starts_with("cache\t"),
+                None => true,
+            };
+            if !starts_entry {
+                let line = line.unwrap_or_default();
+                match &mut entry {
+                    Some((_, _, _, records)) => {
+                        records.push_str(line);
+                        records.push('\n');
+                    }
+                    None if line.is_empty() || line.starts_with('#') => (),
+                    None => {
+                        return Err(CacheError::Parse {
+                            line: line_number,
+                            message: String::from("report before any cache entry"),
+                        })
+                    }
+                }
+                continue;
+            }
+
+            if let Some((start, saved, key, records)) = entry.take() {
+                let reports = fixture::parse(&records).map_err(|e| match e {
+                    FixtureError::Parse { line, message } => CacheError::Parse {
+                        line: start + line,
+                        message,
+                    },
+                    FixtureError::Io(e) => CacheError::Io(e),
+                })?;
+                let report = match <[_; 1]>::try_from(reports) {
+                    Ok([report]) => report,
+                    Err(_) => {
+                        return Err(CacheError::Parse {
+                            line: start,
+                            message: String::from("expected the report of one repo"),
+                        })
+                    }
+                };
+                entries.insert(report.path.clone(), Entry { saved, key, report });
+            }
+            if let Some(line) = line {
+                let bad = || CacheError::Parse {
+                    line: line_number,
+                    message: String::from("expected cache time and key"),
+                };
+                let (saved, key) = match line.split('\t').collect::<Vec<_>>()[..] {
+                    [_, saved, key] => (saved, key),
+                    _ => return Err(bad()),
+                };
+                let saved = saved.parse().map_err(|_| bad())?;
+                entry = Some((line_number, saved, key.to_owned(), String::new()));
+            }
+        }
+        Ok(Self { entries })
+    }
+
+    /// Write the cache, for the next scan to load.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::from("# Results of each repo cached by kamino, for --cache-ttl\n");
+        for entry in self.entries.values() {
+            let _ = writeln!(text, "cache\t{}\t{}", entry.saved, entry.key);
+            text.push_str(&fixture::to_text(std::slice::from_ref(&entry.report)));
+        }
+        crate::report::write(path, &text)
+    }
+
+    /// The report of the repo at `path`, if it was cached less than `ttl` before `now` and the repo's key is still
+    /// `key`.
+    pub fn get(&self, path: &Path, key: &str, ttl: Duration, now: i64) -> Option<&RepoReport> {
+        let entry = self.entries.get(path)?;
+        let age = now.saturating_sub(entry.saved);
+        (entry.key == key && (0..ttl.as_secs() as i64).contains(&age)).then_some(&entry.report)
+    }
+
+    /// Cache the report of a repo, in place of what it had before. The report's fetches are dropped, since a
+    /// repo skipped because of the cache isn't fetched.
+    pub fn insert(&mut self, key: String, mut report: RepoReport, now: i64) {
+        report.fetches.clear();
+        self.entries.insert(
+            report.path.clone(),
+            Entry {
+                saved: now,
+                key,
+                report,
+            },
+        );
+    }
+}
+
+/// What the results of checking a repo depend on, so that they are only reused while none of it changes: what HEAD
+/// and every other reference point at, when the index and `FETCH_HEAD` were last written, when the working
+/// directory or anything directly in it was last modified, and `settings`, e.g. the config the repo is checked
+/// with. As with [`kamino_core::last_modified()`], editing a file in a subdirectory without staging it isn't
+/// noticed.
+pub fn key(repo: &Repository, settings: &str) -> String {
+    let mut hasher = Sha256::new();
+    match repo.head() {
+        Ok(head) => hasher.update(format!(
+            "HEAD {} {}\n",
+            head.name().unwrap_or_default(),
+            head.target().map(|oid| oid.to_string()).unwrap_or_default()
+        )),
+        Err(_) => hasher.update("HEAD -\n"),
+    }
+    if let Ok(references) = repo.references() {
+        for reference in references.flatten() {
+            hasher.update(format!(
+                "{} {}\n",
+                String::from_utf8_lossy(reference.name_bytes()),
+                reference
+                    .target()
+                    .map(|oid| oid.to_string())
+                    .or_else(|| reference.symbolic_target().map(ToOwned::to_owned))
+                    .unwrap_or_default()
+            ));
+        }
+    }
+    for file in ["index", "FETCH_HEAD"] {
+        hasher.update(format!("{file} {}\n", modified(&repo.path().join(file))));
+    }
+    if let Some(workdir) = repo.workdir() {
+        let mut paths = vec![workdir.to_owned()];
+        if let Ok(entries) = fs::read_dir(workdir) {
+            paths.extend(entries.flatten().map(|entry| entry.path()));
+        }
+        paths.sort();
+        for path in paths.iter().filter(|path| *path != repo.path()) {
+            hasher.update(format!("{} {}\n", path.display(), modified(path)));
+        }
+    }
+    hasher.update(settings);
+    format!("{:x}", hasher.finalize())
+}
+
+// When a file was last modified, as precisely as the filesystem says, or 0 if it can't be read. Seconds aren't
+// enough, since the scan that cached the results might be in the same second as a change.
+fn modified(path: &Path) -> u128 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_nanos())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::findings::{Check, Finding};
+    use git2::Signature;
+    use kamino_core::FetchStats;
+
+    #[test]
+    fn save_load() {
+        let mut cache = Cache::default();
+        let mut fetched = RepoReport::for_test(
+            "kamino",
+            vec![Finding::new(Check::Uncommitted, "Has uncommitted changes")],
+        );
+        fetched.summary.repos = 1;
+        fetched.fetches.push(FetchStats {
+            remote: "origin".into(),
+            received_bytes: 10,
+            received_objects: 1,
+            duration: Duration::from_millis(5),
+        });
+        cache.insert("abc".into(), fetched, 1000);
+        cache.insert(
+            "def".into(),
+            RepoReport::for_test("clean", Vec::new()),
+            1100,
+        );
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(CACHE_FILENAME);
+        assert_eq!(Cache::load(&path).unwrap(), Cache::default());
+        cache.save(&path).unwrap();
+        let loaded = Cache::load(&path).unwrap();
+        assert_eq!(loaded, cache);
+
+        let ttl = Duration::from_secs(3600);
+        let kamino = Path::new("/repos/kamino");
+        let cached = loaded.get(kamino, "abc", ttl, 2000).unwrap();
+        assert_eq!(cached.findings.len(), 1);
+        assert!(cached.fetches.is_empty());
+        // A different key, an expired entry or an unknown repo is a miss
+        assert!(loaded.get(kamino, "xyz", ttl, 2000).is_none());
+        assert!(loaded.get(kamino, "abc", ttl, 4600).is_none());
+        assert!(loaded
+            .get(Path::new("/repos/other"), "abc", ttl, 2000)
+            .is_none());
+        assert!(loaded
+            .get(Path::new("/repos/clean"), "def", ttl, 2000)
+            .is_some());
+
+        fs::write(&path, "cache\t1000\tabc\nrepo\ta\t./a\t-\nfinding\tbogus\n").unwrap();
+        assert_eq!(
+            Cache::load(&path).unwrap_err().to_string(),
+            "line 3: unknown check \"bogus\""
+        );
+        fs::write(&path, "repo\ta\t./a\t-\n").unwrap();
+        assert_eq!(
+            Cache::load(&path).unwrap_err().to_string(),
+            "line 1: report before any cache entry"
+        );
+        fs::write(&path, "cache\tsoon\tabc\n").unwrap();
+        assert_eq!(
+            Cache::load(&path).unwrap_err().to_string(),
+            "line 1: expected cache time and key"
+        );
+        fs::write(&path, "cache\t1000\tabc\n").unwrap();
+        assert_eq!(
+            Cache::load(&path).unwrap_err().to_string(),
+            "line 1: expected the report of one repo"
+        );
+    }
+
+    #[test]
+    fn keys() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let unborn = key(&repo, "");
+        assert_eq!(key(&repo, ""), unborn);
+        assert_ne!(key(&repo, "other settings"), unborn);
+
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let commit = repo
+            .commit(Some("HEAD"), &sig, &sig, "first", &tree, &[])
+            .unwrap();
+        let committed = key(&repo, "");
+        assert_ne!(committed, unborn);
+
+        // A new branch changes nothing in the working directory, but may be unpushed
+        repo.branch("feature", &repo.find_commit(commit).unwrap(), false)
+            .unwrap();
+        let branched = key(&repo, "");
+        assert_ne!(branched, committed);
+
+        fs::write(dir.path().join("file"), "contents").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("file")).unwrap();
+        index.write().unwrap();
+        assert_ne!(key(&repo, ""), branched);
+    }
+}
@@ -0,0 +1,136 @@
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+/// Name of the file kept in the scanned directory with the time of the last scan, for `--since-last-scan`.
+pub const LAST_SCAN_FILENAME: &str = ".kaminolastscan";
+
+/// Seconds in a day.
+pub const DAY: i64 = 24 * 60 * 60;
+
+/// Repos with no activity before this time are skipped. Seconds since the Unix epoch.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Cutoff(pub i64);
+
+/// Parses a date as `YYYY-MM-DD`, meaning the start of that day in UTC.
+impl FromStr for Cutoff {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || format!("bad date \"{s}\" (expected YYYY-MM-DD)");
+        let mut parts = s.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+        let (year, month, day) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(Some(year)), Some(Some(month)), Some(Some(day))) => (year, month, day),
+            _ => return Err(bad()),
+        };
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return Err(bad());
+        }
+        let days = days_from_civil(year, month, day);
+        // Days past the end of the month roll over into the next one
+        if civil_from_days(days) != (year, month, day) {
+            return Err(bad());
+        }
+        Ok(Self(days * DAY))
+    }
+}
+
+impl fmt::Display for Cutoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_time(self.0))
+    }
+}
+
+/// Format a time in seconds since the Unix epoch as a UTC date, and also the time of day unless it is midnight.
+pub fn format_time(time: i64) -> String {
+    let (year, month, day) = civil_from_days(time.div_euclid(DAY));
+    let seconds = time.rem_euclid(DAY);
+    if seconds == 0 {
+        format!("{year:04}-{month:02}-{day:02}")
+    } else {
+        format!(
+            "{year:04}-{month:02}-{day:02} {:02}:{:02} UTC",
+            seconds / 3600,
+            seconds % 3600 / 60
+        )
+    }
+}
+
+/// Read the time of the last scan recorded by [`save_last_scan()`], or None if there wasn't one.
+pub fn load_last_scan(path: &Path) -> io::Result<Option<Cutoff>> {
+    match fs::read_to_string(path) {
+        Ok(text) => text
+            .trim()
+            .parse()
+            .map(|time| Some(Cutoff(time)))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Record the time a scan started, so that the next `--since-last-scan` only looks at repos changed since then.
+pub fn save_last_scan(path: &Path, time: i64) -> io::Result<()> {
+    fs::write(path, format!("{time}\n"))
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+/// From <http://howardhinnant.github.io/date_algorithms.html>
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of [`days_from_civil()`].
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        assert_eq!("1970-01-01".parse(), Ok(Cutoff(0)));
+        assert_eq!("2024-01-01".parse(), Ok(Cutoff(1_704_067_200)));
+        assert_eq!("2024-02-29".parse(), Ok(Cutoff(1_709_164_800)));
+        for bad in [
+            "2023-02-29",
+            "2024-13-01",
+            "2024-01",
+            "yesterday",
+            "2024-01-01x",
+        ] {
+            assert!(bad.parse::<Cutoff>().is_err(), "{bad}");
+        }
+
+        assert_eq!(Cutoff(1_709_164_800).to_string(), "2024-02-29");
+        assert_eq!(format_time(1_709_164_800 + 90 * 60), "2024-02-29 01:30 UTC");
+        assert_eq!(format_time(-DAY), "1969-12-31");
+    }
+
+    #[test]
+    fn last_scan() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(LAST_SCAN_FILENAME);
+        assert_eq!(load_last_scan(&path).unwrap(), None);
+        save_last_scan(&path, 1_704_067_200).unwrap();
+        assert_eq!(load_last_scan(&path).unwrap(), Some(Cutoff(1_704_067_200)));
+
+        fs::write(&path, "garbage").unwrap();
+        assert!(load_last_scan(&path).is_err());
+    }
+}
@@ -0,0 +1,1221 @@
+use crate::{
+    changed::Cutoff,
+    findings::{Check, FailOn, Fix},
+    glob::Glob,
+    lifecycle::Thresholds,
+};
+use kamino_core::{BranchFilter, FetchPolicy, ScanOptions};
+use std::{collections::BTreeMap, fmt, path::Path, time::Duration};
+
+/// Name of the config file looked for in the scanned directory.
+pub const DEFAULT_FILENAME: &str = ".kaminoconfig";
+
+/// Tracked files larger than this are reported by the `large-files` check, unless `largeFiles.threshold` says
+/// otherwise.
+pub const DEFAULT_LARGE_FILE_THRESHOLD: u64 = 10 * 1024 * 1024;
+
+/// Settings for a scan, read from a git-config style file.
+///
+/// ```text
+/// [kamino]
+///     remote = origin
+///     allRemotes = true
+///     fetchTimeout = 60
+///     fetchesPerHost = 4
+///     fetchTtl = 1h
+///     cacheTtl = 1h
+///     maxFetchAge = 7d
+///     prune = true
+///     refspec = main
+///     branches = main,develop
+///     base = origin/HEAD
+///     staleAfter = 180d
+///     staleActivity = true
+///     askCredentials = true
+///     storeCredentials = true
+///     failOn = warning
+///     quiet = true
+///     quietWhenClean = true
+///     skip = hooks
+///     fix = hooks
+/// [scan]
+///     exclude = old-*
+///     strictDiscovery = true
+/// [uncommitted]
+///     ignoreSubmodulePointers = true
+/// [policy]
+///     remoteUrl = git@github.com:myorg/*
+/// [merged]
+///     goneOnly = true
+/// [stashed]
+///     staleDays = 30
+/// [hooks]
+///     source = .githooks
+///     source = .lefthook-generated
+///     central = https://github.com/myorg/hooks.git
+/// [repo "some-repo"]
+///     remote = upstream
+///     skip = stashed
+///     email = *@users.noreply.github.com
+///     hookSource = .husky
+///     refspec = develop
+/// [mqtt]
+///     broker = homeassistant.local:1883
+///     topic = kamino
+/// [share]
+///     to = gist
+/// [notify]
+///     webhook = https://hooks.slack.com/services/...
+/// [identity]
+///     name = ?* ?*
+///     email = *@example.com
+/// [signatures]
+///     key = SHA256:...
+/// [largeFiles]
+///     threshold = 10m
+/// [escalate]
+///     stashed = 14
+/// [lifecycle]
+///     newDays = 14
+///     staleDays = 90
+///     archivedDays = 365
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Config {
+    /// Name of the remote to fetch and compare against.
+    pub remote: String,
+    /// Fetch from every remote and compare branches with all of them, not just `remote`.
+    pub all_remotes: bool,
+    /// How long to wait for each fetch before giving up on it.
+    pub fetch_timeout: Option<Duration>,
+    /// How many fetches from the same host may run at once, including ones given up on which haven't stopped yet.
+    pub fetches_per_host: Option<u64>,
+    /// Don't fetch repos fetched more recently than this.
+    pub fetch_ttl: Option<Duration>,
+    /// Reuse the results of repos which haven't changed since they were checked less than this long ago.
+    pub cache_ttl: Option<Duration>,
+    /// Report repos which haven't been fetched for longer than this.
+    pub max_fetch_age: Option<Duration>,
+    /// Prune remote-tracking branches deleted on the remote while fetching.
+    pub prune: bool,
+    /// Refspecs to fetch instead of each remote's configured ones. Empty means the configured ones.
+    pub refspecs: Vec<String>,
+    /// Which local branches to compare with their upstreams.
+    pub branches: BranchFilter,
+    /// Compare every local branch with this base too.
+    pub base: Option<String>,
+    /// Report repos with no commits for this long.
+    pub stale_after: Option<Duration>,
+    /// Only report repos as stale if they haven't been checked out, pulled and so on for that long either.
+    pub stale_activity: bool,
+    /// Ask for credentials on the terminal when nothing else has them.
+    pub ask_credentials: bool,
+    /// Store credentials that were asked for with git's credential helper.
+    pub store_credentials: bool,
+    /// Conditions that make the program exit with a failure code.
+    pub fail_on: Vec<FailOn>,
+    /// Print only findings, without banners or the summary.
+    pub quiet: bool,
+    /// Print nothing if there are no findings.
+    pub quiet_when_clean: bool,
+    /// Checks which are not run.
+    pub skip: Vec<Check>,
+    /// Problems to fix automatically.
+    pub fix: Vec<Fix>,
+    /// Only report what fixes would do. This is only set from the command line, so that a config file can't
+    /// quietly turn fixes off.
+    pub dry_run: bool,
+    /// Only check for uncommitted changes and the checked-out branch, without fetching. Also only set from the
+    /// command line.
+    pub quick: bool,
+    /// Also list repos with no findings. Also only set from the command line.
+    pub verbose: bool,
+    /// Show how mismatched hooks differ. Also only set from the command line.
+    pub diff: bool,
+    /// Skip repos with no activity since this time. Also only set from the command line.
+    pub changed_since: Option<Cutoff>,
+    /// Don't count submodules which only point at another commit as uncommitted changes.
+    pub ignore_submodule_pointers: bool,
+    /// Patterns the remote's URL must match one of, if any are given.
+    pub allowed_urls: Vec<Glob>,
+    /// Only report merged branches whose upstream branch was deleted.
+    pub merged_gone_only: bool,
+    /// Report stashes older than this many days on their own.
+    pub stale_stash_days: Option<u32>,
+    /// In-repo hook directories, in order of precedence. Empty means just `.githooks`.
+    pub hook_sources: Vec<String>,
+    /// Local path or URL of a repo with the canonical versions of hooks, which every repo should have.
+    pub central_hooks: Option<String>,
+    /// Directory names which are not scanned.
+    pub exclude: Vec<Glob>,
+    /// Fail if any directory can't be read, rather than skipping it.
+    pub strict_discovery: bool,
+    /// Overrides for individual repos, keyed by directory name.
+    pub repos: BTreeMap<String, RepoConfig>,
+    /// MQTT broker (`host`, `host:port` or `[ipv6]:port`) to publish results to.
+    pub mqtt_broker: Option<String>,
+    /// MQTT topic prefix to publish results under.
+    pub mqtt_topic: String,
+    /// Where `kamino report share` uploads to: `gist` or a URL.
+    pub share_to: Option<String>,
+    /// URL to POST the findings to when there are any.
+    pub notify_webhook: Option<String>,
+    /// When repos move between lifecycle stages.
+    pub lifecycle: Thresholds,
+    /// Tracked files larger than this many bytes are reported.
+    pub large_file_threshold: u64,
+    /// Pattern `user.name` should match in every repo.
+    pub identity_name: Option<Glob>,
+    /// Pattern `user.email` should match, unless the repo has its own.
+    pub identity_email: Option<Glob>,
+    /// Keys unpushed commits should be signed with. If any are given, every repo is expected to sign commits, not
+    /// just those with `commit.gpgSign` set.
+    pub signing_keys: Vec<String>,
+    /// Findings from these checks go up a level for every so many days they have been around.
+    pub escalate: Vec<(Check, u32)>,
+}
+
+/// Settings which apply to a single repo.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RepoConfig {
+    /// Name of the remote to fetch and compare against, instead of the global one.
+    pub remote: Option<String>,
+    /// Checks which are not run, in addition to the global ones.
+    pub skip: Vec<Check>,
+    /// Pattern `user.email` should match, instead of the global one.
+    pub email: Option<Glob>,
+    /// In-repo hook directories, in order of precedence, instead of the global ones.
+    pub hook_sources: Vec<String>,
+    /// Refspecs to fetch, instead of the global ones.
+    pub refspecs: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            remote: String::from("origin"),
+            all_remotes: false,
+            fetch_timeout: None,
+            fetches_per_host: None,
+            fetch_ttl: None,
+            cache_ttl: None,
+            max_fetch_age: None,
+            prune: false,
+            refspecs: Vec::new(),
+            branches: BranchFilter::All,
+            base: None,
+            stale_after: None,
+            stale_activity: false,
+            ask_credentials: false,
+            store_credentials: false,
+            fail_on: Vec::new(),
+            quiet: false,
+            quiet_when_clean: false,
+            skip: Vec::new(),
+            fix: Vec::new(),
+            dry_run: false,
+            quick: false,
+            verbose: false,
+            diff: false,
+            changed_since: None,
+            ignore_submodule_pointers: false,
+            allowed_urls: Vec::new(),
+            merged_gone_only: false,
+            stale_stash_days: None,
+            hook_sources: Vec::new(),
+            central_hooks: None,
+            exclude: Vec::new(),
+            strict_discovery: false,
+            repos: BTreeMap::new(),
+            mqtt_broker: None,
+            mqtt_topic: String::from("kamino"),
+            share_to: None,
+            notify_webhook: None,
+            lifecycle: Thresholds::default(),
+            large_file_threshold: DEFAULT_LARGE_FILE_THRESHOLD,
+            identity_name: None,
+            identity_email: None,
+            signing_keys: Vec::new(),
+            escalate: Vec::new(),
+        }
+    }
+}
+
+/// Error type for [`Config::load()`].
+#[derive(thiserror::Error, Debug)]
+#[error("failed to read config file {path}")]
+pub struct ConfigError {
+    /// Path to the config file.
+    path: String,
+    /// Underlying error.
+    source: git2::Error,
+}
+
+/// Something wrong with the config file which doesn't stop it from being used.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Problem(pub String);
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Config {
+    /// Read all entries of a config file, in the order they appear.
+    ///
+    /// # Errors
+    ///
+    /// See [`ConfigError`].
+    pub fn read_entries(path: &Path) -> Result<Vec<(String, String)>, ConfigError> {
+        let map_err = |e| ConfigError {
+            path: path.display().to_string(),
+            source: e,
+        };
+        let file = git2::Config::open(path).map_err(map_err)?;
+        let mut entries = Vec::new();
+        file.entries(None)
+            .map_err(map_err)?
+            .for_each(|entry| {
+                entries.push((
+                    entry.name().unwrap_or_default().to_owned(),
+                    entry.value().unwrap_or_default().to_owned(),
+                ));
+            })
+            .map_err(map_err)?;
+        Ok(entries)
+    }
+
+    /// Load the config file, starting from the defaults. Values which can't be understood are skipped and
+    /// reported as problems, along with any settings which conflict.
+    ///
+    /// # Errors
+    ///
+    /// See [`ConfigError`].
+    pub fn load(path: &Path) -> Result<(Self, Vec<Problem>), ConfigError> {
+        let entries = Self::read_entries(path)?;
+        Ok(Self::from_entries(&entries))
+    }
+
+    /// Build a config from `(name, value)` pairs, where names are normalized the way git does: section and key
+    /// lowercase, subsection as written.
+    pub fn from_entries(entries: &[(String, String)]) -> (Self, Vec<Problem>) {
+        let mut config = Self::default();
+        let mut problems = Vec::new();
+
+        for (name, value) in entries {
+            let unknown = || Problem(format!("unknown key \"{name}\""));
+            match name.as_str() {
+                "kamino.remote" => config.remote = value.clone(),
+                "kamino.failon" => match value.parse() {
+                    Ok(fail_on) => config.fail_on.push(fail_on),
+                    Err(e) => problems.push(Problem(format!("{name}: {e}"))),
+                },
+                "kamino.allremotes" => match git2::Config::parse_bool(value.as_str()) {
+                    Ok(b) => config.all_remotes = b,
+                    Err(_) => {
+                        problems.push(Problem(format!("{name}: \"{value}\" is not a boolean")))
+                    }
+                },
+                "kamino.fetchtimeout" => match value.parse() {
+                    Ok(seconds) if seconds > 0 => {
+                        config.fetch_timeout = Some(Duration::from_secs(seconds))
+                    }
+                    _ => problems.push(Problem(format!(
+                        "{name}: \"{value}\" is not a number of seconds"
+                    ))),
+                },
+                "kamino.fetchesperhost" => match value.parse() {
+                    Ok(limit) if limit > 0 => config.fetches_per_host = Some(limit),
+                    _ => problems.push(Problem(format!(
+                        "{name}: \"{value}\" is not a number of fetches"
+                    ))),
+                },
+                "kamino.fetchttl" => match parse_duration(value) {
+                    Ok(ttl) => config.fetch_ttl = Some(ttl),
+                    Err(e) => problems.push(Problem(format!("{name}: {e}"))),
+                },
+                "kamino.cachettl" => match parse_duration(value) {
+                    Ok(ttl) => config.cache_ttl = Some(ttl),
+                    Err(e) => problems.push(Problem(format!("{name}: {e}"))),
+                },
+                "kamino.maxfetchage" => match parse_duration(value) {
+                    Ok(age) => config.max_fetch_age = Some(age),
+                    Err(e) => problems.push(Problem(format!("{name}: {e}"))),
+                },
+                "kamino.prune" => match git2::Config::parse_bool(value.as_str()) {
+                    Ok(b) => config.prune = b,
+                    Err(_) => {
+                        problems.push(Problem(format!("{name}: \"{value}\" is not a boolean")))
+                    }
+                },
+                "kamino.refspec" => config.refspecs.push(value.clone()),
+                "kamino.branches" => match parse_branches(value) {
+                    Ok(branches) => config.branches = branches,
+                    Err(e) => problems.push(Problem(format!("{name}: {e}"))),
+                },
+                "kamino.base" => config.base = Some(value.clone()),
+                "kamino.staleafter" => match parse_duration(value) {
+                    Ok(after) => config.stale_after = Some(after),
+                    Err(e) => problems.push(Problem(format!("{name}: {e}"))),
+                },
+                "kamino.staleactivity" => match git2::Config::parse_bool(value.as_str()) {
+                    Ok(b) => config.stale_activity = b,
+                    Err(_) => {
+                        problems.push(Problem(format!("{name}: \"{value}\" is not a boolean")))
+                    }
+                },
+                "kamino.askcredentials" => match git2::Config::parse_bool(value.as_str()) {
+                    Ok(b) => config.ask_credentials = b,
+                    Err(_) => {
+                        problems.push(Problem(format!("{name}: \"{value}\" is not a boolean")))
+                    }
+                },
+                "kamino.storecredentials" => match git2::Config::parse_bool(value.as_str()) {
+                    Ok(b) => config.store_credentials = b,
+                    Err(_) => {
+                        problems.push(Problem(format!("{name}: \"{value}\" is not a boolean")))
+                    }
+                },
+                "kamino.quiet" => match git2::Config::parse_bool(value.as_str()) {
+                    Ok(b) => config.quiet = b,
+                    Err(_) => {
+                        problems.push(Problem(format!("{name}: \"{value}\" is not a boolean")))
+                    }
+                },
+                "kamino.quietwhenclean" => match git2::Config::parse_bool(value.as_str()) {
+                    Ok(b) => config.quiet_when_clean = b,
+                    Err(_) => {
+                        problems.push(Problem(format!("{name}: \"{value}\" is not a boolean")))
+                    }
+                },
+                "kamino.skip" => parse_checks(name, value, &mut config.skip, &mut problems),
+                "kamino.fix" => {
+                    for item in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                        match item.parse() {
+                            Ok(fix) => config.fix.push(fix),
+                            Err(e) => problems.push(Problem(format!("{name}: {e}"))),
+                        }
+                    }
+                }
+                "scan.exclude" => match value.parse() {
+                    Ok(glob) => config.exclude.push(glob),
+                    Err(e) => problems.push(Problem(format!("{name}: bad glob: {e}"))),
+                },
+                "scan.strictdiscovery" => match git2::Config::parse_bool(value.as_str()) {
+                    Ok(b) => config.strict_discovery = b,
+                    Err(_) => {
+                        problems.push(Problem(format!("{name}: \"{value}\" is not a boolean")))
+                    }
+                },
+                "uncommitted.ignoresubmodulepointers" => {
+                    match git2::Config::parse_bool(value.as_str()) {
+                        Ok(b) => config.ignore_submodule_pointers = b,
+                        Err(_) => {
+                            problems.push(Problem(format!("{name}: \"{value}\" is not a boolean")))
+                        }
+                    }
+                }
+                "policy.remoteurl" => match value.parse() {
+                    Ok(glob) => config.allowed_urls.push(glob),
+                    Err(e) => problems.push(Problem(format!("{name}: bad glob: {e}"))),
+                },
+                "merged.goneonly" => match git2::Config::parse_bool(value.as_str()) {
+                    Ok(b) => config.merged_gone_only = b,
+                    Err(_) => {
+                        problems.push(Problem(format!("{name}: \"{value}\" is not a boolean")))
+                    }
+                },
+                "stashed.staledays" => match value.parse() {
+                    Ok(days) if days > 0 => config.stale_stash_days = Some(days),
+                    _ => problems.push(Problem(format!(
+                        "{name}: \"{value}\" is not a number of days"
+                    ))),
+                },
+                "hooks.source" => config.hook_sources.push(value.clone()),
+                "hooks.central" => config.central_hooks = Some(value.clone()),
+                "mqtt.broker" => config.mqtt_broker = Some(value.clone()),
+                "mqtt.topic" => config.mqtt_topic = value.clone(),
+                "share.to" => config.share_to = Some(value.clone()),
+                "notify.webhook" => config.notify_webhook = Some(value.clone()),
+                "lifecycle.newdays" => {
+                    parse_days(name, value, &mut config.lifecycle.new_days, &mut problems)
+                }
+                "lifecycle.staledays" => {
+                    parse_days(name, value, &mut config.lifecycle.stale_days, &mut problems)
+                }
+                "lifecycle.archiveddays" => parse_days(
+                    name,
+                    value,
+                    &mut config.lifecycle.archived_days,
+                    &mut problems,
+                ),
+                "identity.name" => match value.parse() {
+                    Ok(glob) => config.identity_name = Some(glob),
+                    Err(e) => problems.push(Problem(format!("{name}: bad glob: {e}"))),
+                },
+                "identity.email" => match value.parse() {
+                    Ok(glob) => config.identity_email = Some(glob),
+                    Err(e) => problems.push(Problem(format!("{name}: bad glob: {e}"))),
+                },
+                "signatures.key" => config.signing_keys.push(value.clone()),
+                // Sizes can have a k, m or g suffix, as in git's own config
+                "largefiles.threshold" => match git2::Config::parse_i64(value.as_str()) {
+                    Ok(bytes) if bytes > 0 => config.large_file_threshold = bytes as u64,
+                    _ => problems.push(Problem(format!("{name}: \"{value}\" is not a size"))),
+                },
+                _ if name.starts_with("escalate.") => {
+                    let check = &name["escalate.".len()..];
+                    match Check::ALL.into_iter().find(|c| c.as_str() == check) {
+                        Some(check) => match value.parse() {
+                            Ok(days) if days > 0 => {
+                                config.escalate.retain(|(c, _)| *c != check);
+                                config.escalate.push((check, days));
+                            }
+                            _ => problems.push(Problem(format!(
+                                "{name}: \"{value}\" is not a number of days"
+                            ))),
+                        },
+                        None => {
+                            problems.push(Problem(format!("{name}: unknown check \"{check}\"")))
+                        }
+                    }
+                }
+                _ => {
+                    // Per-repo sections look like `repo.<dir name>.<key>`, where the dir name may contain dots
+                    let repo_key = name
+                        .strip_prefix("repo.")
+                        .and_then(|rest| rest.rsplit_once('.'));
+                    match repo_key {
+                        Some((repo, "remote")) => {
+                            config.repos.entry(repo.to_owned()).or_default().remote =
+                                Some(value.clone());
+                        }
+                        Some((repo, "skip")) => {
+                            let repo_config = config.repos.entry(repo.to_owned()).or_default();
+                            parse_checks(name, value, &mut repo_config.skip, &mut problems);
+                        }
+                        Some((repo, "hooksource")) => {
+                            let repo_config = config.repos.entry(repo.to_owned()).or_default();
+                            repo_config.hook_sources.push(value.clone());
+                        }
+                        Some((repo, "refspec")) => {
+                            let repo_config = config.repos.entry(repo.to_owned()).or_default();
+                            repo_config.refspecs.push(value.clone());
+                        }
+                        Some((repo, "email")) => match value.parse() {
+                            Ok(glob) => {
+                                config.repos.entry(repo.to_owned()).or_default().email = Some(glob);
+                            }
+                            Err(e) => problems.push(Problem(format!("{name}: bad glob: {e}"))),
+                        },
+                        _ => problems.push(unknown()),
+                    }
+                }
+            }
+        }
+
+        problems.extend(config.conflicts());
+        (config, problems)
+    }
+
+    /// Find settings which contradict each other.
+    fn conflicts(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        for fail_on in &self.fail_on {
+            if let FailOn::Check(check) = fail_on {
+                if self.skip.contains(check) {
+                    problems.push(Problem(format!(
+                        "kamino.failOn \"{check}\" can never match because the check is skipped"
+                    )));
+                }
+            }
+        }
+
+        if cfg!(not(feature = "mqtt")) && self.mqtt_broker.is_some() {
+            problems.push(Problem(String::from(
+                "mqtt.broker has no effect because kamino was built without the mqtt feature",
+            )));
+        }
+
+        if cfg!(not(feature = "share")) && self.share_to.is_some() {
+            problems.push(Problem(String::from(
+                "share.to has no effect because kamino was built without the share feature",
+            )));
+        }
+
+        if cfg!(not(feature = "webhook")) && self.notify_webhook.is_some() {
+            problems.push(Problem(String::from(
+                "notify.webhook has no effect because kamino was built without the webhook feature",
+            )));
+        }
+
+        if self.lifecycle.stale_days >= self.lifecycle.archived_days {
+            problems.push(Problem(String::from(
+                "lifecycle.staleDays should be less than lifecycle.archivedDays, otherwise repos are never stale",
+            )));
+        }
+
+        for repo in self.repos.keys() {
+            if self.is_excluded(repo) {
+                problems.push(Problem(format!(
+                    "settings for repo \"{repo}\" have no effect because it is excluded by scan.exclude"
+                )));
+            }
+        }
+
+        problems
+    }
+
+    /// In-repo hook directories to compare against `.git/hooks` in the repo with this directory name, in order of
+    /// precedence.
+    pub fn hook_sources_for(&self, dir_name: &str) -> Vec<&str> {
+        let sources = match self.repos.get(dir_name) {
+            Some(repo) if !repo.hook_sources.is_empty() => &repo.hook_sources,
+            _ => &self.hook_sources,
+        };
+        if sources.is_empty() {
+            vec![kamino_core::DEFAULT_HOOKS_DIR]
+        } else {
+            sources.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// The refspecs to fetch for the repo with this directory name. Empty means each remote's configured ones.
+    pub fn refspecs_for(&self, dir_name: &str) -> &[String] {
+        match self.repos.get(dir_name) {
+            Some(repo) if !repo.refspecs.is_empty() => &repo.refspecs,
+            _ => &self.refspecs,
+        }
+    }
+
+    /// The library's options for checking the repo with this directory name. Central hooks are left out, since a
+    /// URL has to be cloned first.
+    pub fn scan_options(&self, repo: &str) -> ScanOptions {
+        let fetch = if self.quick {
+            FetchPolicy::Never
+        } else if self.all_remotes {
+            FetchPolicy::AllRemotes
+        } else {
+            FetchPolicy::Remote
+        };
+        ScanOptions::new()
+            .remote(self.remote_for(repo))
+            .fetch(fetch)
+            .fetch_timeout(self.fetch_timeout)
+            .fetch_ttl(self.fetch_ttl)
+            .prune(self.prune)
+            .refspecs(self.refspecs_for(repo))
+            .branches(self.branches.clone())
+            .hook_sources(self.hook_sources_for(repo))
+    }
+
+    /// Whether a remote URL is allowed by the URL policy. Any URL is allowed if there is no policy.
+    pub fn is_url_allowed(&self, url: &str) -> bool {
+        self.allowed_urls.is_empty() || self.allowed_urls.iter().any(|glob| glob.matches(url))
+    }
+
+    /// Whether the repo with this directory name should not be scanned.
+    pub fn is_excluded(&self, dir_name: &str) -> bool {
+        self.exclude.iter().any(|glob| glob.matches(dir_name))
+    }
+
+    /// The remote to use for the repo with this directory name.
+    pub fn remote_for(&self, dir_name: &str) -> &str {
+        self.repos
+            .get(dir_name)
+            .and_then(|repo| repo.remote.as_deref())
+            .unwrap_or(&self.remote)
+    }
+
+    /// The pattern `user.email` should match in the repo with this directory name, if there is one.
+    pub fn email_for(&self, dir_name: &str) -> Option<&Glob> {
+        self.repos
+            .get(dir_name)
+            .and_then(|repo| repo.email.as_ref())
+            .or(self.identity_email.as_ref())
+    }
+
+    /// How many days a finding from this check is around before it goes up a level, if it ever does.
+    pub fn escalation_for(&self, check: Check) -> Option<u32> {
+        self.escalate
+            .iter()
+            .find(|(c, _)| *c == check)
+            .map(|(_, days)| *days)
+    }
+
+    /// Whether a check should be skipped for the repo with this directory name.
+    pub fn is_skipped(&self, dir_name: &str, check: Check) -> bool {
+        self.skip.contains(&check)
+            || self
+                .repos
+                .get(dir_name)
+                .is_some_and(|repo| repo.skip.contains(&check))
+    }
+}
+
+// Parse a comma separated list of check names, reporting any that aren't known.
+fn parse_checks(name: &str, value: &str, out: &mut Vec<Check>, problems: &mut Vec<Problem>) {
+    for item in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        match Check::ALL.into_iter().find(|c| c.as_str() == item) {
+            Some(check) => out.push(check),
+            None => problems.push(Problem(format!("{name}: unknown check \"{item}\""))),
+        }
+    }
+}
+
+// Parse a number of days, reporting it if it isn't one.
+fn parse_days(name: &str, value: &str, out: &mut u32, problems: &mut Vec<Problem>) {
+    match value.parse() {
+        Ok(days) => *out = days,
+        Err(_) => problems.push(Problem(format!(
+            "{name}: \"{value}\" is not a number of days"
+        ))),
+    }
+}
+
+/// Parse which branches to compare: `HEAD` for the checked-out branch, or a comma separated list of branch names or
+/// patterns such as `release/*`.
+pub fn parse_branches(value: &str) -> Result<BranchFilter, String> {
+    if value.trim() == "HEAD" {
+        return Ok(BranchFilter::Current);
+    }
+    let patterns: Vec<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(str::to_owned)
+        .collect();
+    if patterns.is_empty() {
+        return Err(String::from("no branches given"));
+    }
+    Ok(BranchFilter::Matching(patterns))
+}
+
+/// Parse a duration as a number with a unit: `s`, `m`, `h` or `d` for seconds, minutes, hours or days, e.g. `90m`.
+/// A plain number is seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let bad = || format!("\"{value}\" is not a duration (expected e.g. 30s, 15m, 1h or 2d)");
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+    let unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(bad()),
+    };
+    let number: u64 = number.parse().map_err(|_| bad())?;
+    number
+        .checked_mul(unit)
+        .map(Duration::from_secs)
+        .ok_or_else(bad)
+}
+
+// Format a duration with the largest unit that keeps it exact, so that it parses back the same.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    for (suffix, unit) in [("d", 24 * 60 * 60), ("h", 60 * 60), ("m", 60)] {
+        if seconds != 0 && seconds.is_multiple_of(unit) {
+            return format!("{}{suffix}", seconds / unit);
+        }
+    }
+    format!("{seconds}s")
+}
+
+// Format a number of bytes the way git's config does, with the largest suffix that keeps it exact.
+fn format_size(bytes: u64) -> String {
+    for (suffix, unit) in [("g", 1 << 30), ("m", 1 << 20), ("k", 1 << 10)] {
+        if bytes.is_multiple_of(unit) {
+            return format!("{}{suffix}", bytes / unit);
+        }
+    }
+    bytes.to_string()
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Formats the config in the same syntax as the config file.
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[kamino]")?;
+        writeln!(f, "\tremote = {}", self.remote)?;
+        if self.all_remotes {
+            writeln!(f, "\tallRemotes = true")?;
+        }
+        if let Some(timeout) = self.fetch_timeout {
+            writeln!(f, "\tfetchTimeout = {}", timeout.as_secs())?;
+        }
+        if let Some(limit) = self.fetches_per_host {
+            writeln!(f, "\tfetchesPerHost = {limit}")?;
+        }
+        if let Some(ttl) = self.fetch_ttl {
+            writeln!(f, "\tfetchTtl = {}", format_duration(ttl))?;
+        }
+        if let Some(ttl) = self.cache_ttl {
+            writeln!(f, "\tcacheTtl = {}", format_duration(ttl))?;
+        }
+        if let Some(age) = self.max_fetch_age {
+            writeln!(f, "\tmaxFetchAge = {}", format_duration(age))?;
+        }
+        if self.prune {
+            writeln!(f, "\tprune = true")?;
+        }
+        for refspec in &self.refspecs {
+            writeln!(f, "\trefspec = {refspec}")?;
+        }
+        match &self.branches {
+            BranchFilter::All => (),
+            BranchFilter::Current => writeln!(f, "\tbranches = HEAD")?,
+            BranchFilter::Matching(patterns) => writeln!(f, "\tbranches = {}", join(patterns))?,
+        }
+        if let Some(base) = &self.base {
+            writeln!(f, "\tbase = {base}")?;
+        }
+        if let Some(after) = self.stale_after {
+            writeln!(f, "\tstaleAfter = {}", format_duration(after))?;
+        }
+        if self.stale_activity {
+            writeln!(f, "\tstaleActivity = true")?;
+        }
+        if self.ask_credentials {
+            writeln!(f, "\taskCredentials = true")?;
+        }
+        if self.store_credentials {
+            writeln!(f, "\tstoreCredentials = true")?;
+        }
+        for fail_on in &self.fail_on {
+            writeln!(f, "\tfailOn = {fail_on}")?;
+        }
+        writeln!(f, "\tquiet = {}", self.quiet)?;
+        writeln!(f, "\tquietWhenClean = {}", self.quiet_when_clean)?;
+        if !self.skip.is_empty() {
+            writeln!(f, "\tskip = {}", join(&self.skip))?;
+        }
+        if !self.fix.is_empty() {
+            writeln!(f, "\tfix = {}", join(&self.fix))?;
+        }
+
+        if !self.exclude.is_empty() || self.strict_discovery {
+            writeln!(f, "[scan]")?;
+            for glob in &self.exclude {
+                writeln!(f, "\texclude = {glob}")?;
+            }
+            if self.strict_discovery {
+                writeln!(f, "\tstrictDiscovery = true")?;
+            }
+        }
+
+        if self.ignore_submodule_pointers {
+            writeln!(f, "[uncommitted]")?;
+            writeln!(f, "\tignoreSubmodulePointers = true")?;
+        }
+
+        if !self.allowed_urls.is_empty() {
+            writeln!(f, "[policy]")?;
+            for glob in &self.allowed_urls {
+                writeln!(f, "\tremoteUrl = {glob}")?;
+            }
+        }
+
+        if self.merged_gone_only {
+            writeln!(f, "[merged]")?;
+            writeln!(f, "\tgoneOnly = true")?;
+        }
+
+        if let Some(days) = self.stale_stash_days {
+            writeln!(f, "[stashed]")?;
+            writeln!(f, "\tstaleDays = {days}")?;
+        }
+
+        if !self.hook_sources.is_empty() || self.central_hooks.is_some() {
+            writeln!(f, "[hooks]")?;
+            for source in &self.hook_sources {
+                writeln!(f, "\tsource = {source}")?;
+            }
+            if let Some(central) = &self.central_hooks {
+                writeln!(f, "\tcentral = {central}")?;
+            }
+        }
+
+        if let Some(broker) = &self.mqtt_broker {
+            writeln!(f, "[mqtt]")?;
+            writeln!(f, "\tbroker = {broker}")?;
+            writeln!(f, "\ttopic = {}", self.mqtt_topic)?;
+        }
+
+        if let Some(to) = &self.share_to {
+            writeln!(f, "[share]")?;
+            writeln!(f, "\tto = {to}")?;
+        }
+
+        if let Some(url) = &self.notify_webhook {
+            writeln!(f, "[notify]")?;
+            writeln!(f, "\twebhook = {url}")?;
+        }
+
+        if self.identity_name.is_some() || self.identity_email.is_some() {
+            writeln!(f, "[identity]")?;
+            if let Some(glob) = &self.identity_name {
+                writeln!(f, "\tname = {glob}")?;
+            }
+            if let Some(glob) = &self.identity_email {
+                writeln!(f, "\temail = {glob}")?;
+            }
+        }
+
+        if !self.signing_keys.is_empty() {
+            writeln!(f, "[signatures]")?;
+            for key in &self.signing_keys {
+                writeln!(f, "\tkey = {key}")?;
+            }
+        }
+
+        if self.large_file_threshold != DEFAULT_LARGE_FILE_THRESHOLD {
+            writeln!(f, "[largeFiles]")?;
+            writeln!(
+                f,
+                "\tthreshold = {}",
+                format_size(self.large_file_threshold)
+            )?;
+        }
+
+        if !self.escalate.is_empty() {
+            writeln!(f, "[escalate]")?;
+            for (check, days) in &self.escalate {
+                writeln!(f, "\t{check} = {days}")?;
+            }
+        }
+
+        writeln!(f, "[lifecycle]")?;
+        writeln!(f, "\tnewDays = {}", self.lifecycle.new_days)?;
+        writeln!(f, "\tstaleDays = {}", self.lifecycle.stale_days)?;
+        writeln!(f, "\tarchivedDays = {}", self.lifecycle.archived_days)?;
+
+        for (name, repo) in &self.repos {
+            writeln!(f, "[repo \"{name}\"]")?;
+            if let Some(remote) = &repo.remote {
+                writeln!(f, "\tremote = {remote}")?;
+            }
+            if !repo.skip.is_empty() {
+                writeln!(f, "\tskip = {}", join(&repo.skip))?;
+            }
+            if let Some(glob) = &repo.email {
+                writeln!(f, "\temail = {glob}")?;
+            }
+            for source in &repo.hook_sources {
+                writeln!(f, "\thookSource = {source}")?;
+            }
+            for refspec in &repo.refspecs {
+                writeln!(f, "\trefspec = {refspec}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::findings::Level;
+
+    fn entries(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn load() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(DEFAULT_FILENAME);
+        std::fs::write(
+            &path,
+            "[kamino]\n\tremote = upstream\n\tallRemotes = on\n\tfailOn = warning\n\tquiet = no\n\tquietWhenClean = yes\n\
+             [scan]\n\texclude = old-*\n\tstrictDiscovery = true\n[repo \"My.Repo\"]\n\tskip = hooks, stashed\n",
+        )
+        .unwrap();
+
+        let (config, problems) = Config::load(&path).unwrap();
+        assert!(problems.is_empty(), "{problems:?}");
+        assert_eq!(config.remote, "upstream");
+        assert!(config.all_remotes);
+        assert_eq!(config.fail_on, vec![FailOn::Level(Level::Warning)]);
+        assert!(!config.quiet);
+        assert!(config.quiet_when_clean);
+        assert!(config.is_excluded("old-stuff"));
+        assert!(!config.is_excluded("new-stuff"));
+        assert!(config.strict_discovery);
+        assert!(config.is_skipped("My.Repo", Check::Hooks));
+        assert!(!config.is_skipped("other", Check::Hooks));
+        assert_eq!(config.remote_for("My.Repo"), "upstream");
+        assert!(config.is_url_allowed("https://example.com/anything.git"));
+        assert_eq!(config.large_file_threshold, DEFAULT_LARGE_FILE_THRESHOLD);
+    }
+
+    #[test]
+    fn identity() {
+        let (config, problems) = Config::from_entries(&entries(&[
+            ("identity.email", "*@work.example.com"),
+            ("repo.side-project.email", "*@users.noreply.github.com"),
+        ]));
+        assert!(problems.is_empty(), "{problems:?}");
+        assert!(config
+            .email_for("work-repo")
+            .unwrap()
+            .matches("me@work.example.com"));
+        assert!(config
+            .email_for("side-project")
+            .unwrap()
+            .matches("1234+me@users.noreply.github.com"));
+        assert!(config
+            .to_string()
+            .contains("[identity]\n\temail = *@work.example.com\n"));
+        assert_eq!(Config::default().email_for("any"), None);
+    }
+
+    #[test]
+    fn ignore_submodule_pointers() {
+        let (config, problems) =
+            Config::from_entries(&entries(&[("uncommitted.ignoresubmodulepointers", "yes")]));
+        assert!(problems.is_empty(), "{problems:?}");
+        assert!(config.ignore_submodule_pointers);
+        assert!(config
+            .to_string()
+            .contains("[uncommitted]\n\tignoreSubmodulePointers = true\n"));
+        let (_, problems) = Config::from_entries(&entries(&[(
+            "uncommitted.ignoresubmodulepointers",
+            "sometimes",
+        )]));
+        assert_eq!(problems.len(), 1);
+    }
+
+    #[test]
+    fn stale_stash_days() {
+        let (config, problems) = Config::from_entries(&entries(&[("stashed.staledays", "30")]));
+        assert!(problems.is_empty(), "{problems:?}");
+        assert_eq!(config.stale_stash_days, Some(30));
+        assert!(config.to_string().contains("[stashed]\n\tstaleDays = 30\n"));
+        assert!(!Config::default().to_string().contains("[stashed]"));
+        let (_, problems) = Config::from_entries(&entries(&[("stashed.staledays", "0")]));
+        assert_eq!(
+            problems,
+            [Problem(
+                "stashed.staledays: \"0\" is not a number of days".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn signing_keys() {
+        let (config, problems) = Config::from_entries(&entries(&[
+            ("signatures.key", "ABCDEF0123456789"),
+            ("signatures.key", "SHA256:abc"),
+        ]));
+        assert!(problems.is_empty(), "{problems:?}");
+        assert_eq!(config.signing_keys, ["ABCDEF0123456789", "SHA256:abc"]);
+        assert!(config
+            .to_string()
+            .contains("[signatures]\n\tkey = ABCDEF0123456789\n\tkey = SHA256:abc\n"));
+        assert!(!Config::default().to_string().contains("[signatures]"));
+    }
+
+    #[test]
+    fn escalate() {
+        let (config, problems) = Config::from_entries(&entries(&[
+            ("escalate.stashed", "30"),
+            ("escalate.behind", "7"),
+            ("escalate.stashed", "14"),
+            ("escalate.bogus", "7"),
+            ("escalate.ahead", "0"),
+        ]));
+        assert_eq!(
+            problems,
+            [
+                Problem("escalate.bogus: unknown check \"bogus\"".into()),
+                Problem("escalate.ahead: \"0\" is not a number of days".into()),
+            ]
+        );
+        assert_eq!(config.escalation_for(Check::Stashed), Some(14));
+        assert_eq!(config.escalation_for(Check::Behind), Some(7));
+        assert_eq!(config.escalation_for(Check::Ahead), None);
+        assert!(config
+            .to_string()
+            .contains("[escalate]\n\tbehind = 7\n\tstashed = 14\n"));
+    }
+
+    #[test]
+    fn large_file_threshold() {
+        for (value, bytes, shown) in [
+            ("50m", 50 << 20, "50m"),
+            ("1g", 1 << 30, "1g"),
+            ("1500", 1500, "1500"),
+        ] {
+            let (config, problems) =
+                Config::from_entries(&entries(&[("largefiles.threshold", value)]));
+            assert!(problems.is_empty(), "{problems:?}");
+            assert_eq!(config.large_file_threshold, bytes);
+            assert!(config
+                .to_string()
+                .contains(&format!("[largeFiles]\n\tthreshold = {shown}\n")));
+        }
+        assert!(!Config::default().to_string().contains("[largeFiles]"));
+    }
+
+    #[test]
+    fn durations() {
+        for (value, seconds, shown) in [
+            ("45", 45, "45s"),
+            ("90s", 90, "90s"),
+            ("120m", 7200, "2h"),
+            ("1h", 3600, "1h"),
+            ("2d", 172_800, "2d"),
+            ("0m", 0, "0s"),
+        ] {
+            let duration = parse_duration(value).unwrap();
+            assert_eq!(duration, Duration::from_secs(seconds), "{value}");
+            assert_eq!(format_duration(duration), shown);
+        }
+        for bad in ["", "h", "1 h", "1w", "-1h", "1.5h", "99999999999999999999d"] {
+            assert!(parse_duration(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn url_policy() {
+        let (config, problems) = Config::from_entries(&entries(&[
+            ("policy.remoteurl", "git@github.com:myorg/*"),
+            ("policy.remoteurl", "https://github.com/myorg/*"),
+        ]));
+        assert!(problems.is_empty(), "{problems:?}");
+        assert!(config.is_url_allowed("git@github.com:myorg/kamino.git"));
+        assert!(config.is_url_allowed("https://github.com/myorg/kamino"));
+        assert!(!config.is_url_allowed("git@github.com:someone/kamino.git"));
+    }
+
+    #[test]
+    fn scan_options() {
+        assert_eq!(
+            Config::default().scan_options("any"),
+            ScanOptions::default()
+        );
+
+        let (mut config, problems) = Config::from_entries(&entries(&[
+            ("kamino.allremotes", "true"),
+            ("kamino.fetchtimeout", "30"),
+            ("kamino.fetchesperhost", "2"),
+            ("kamino.fetchttl", "90m"),
+            ("kamino.cachettl", "2h"),
+            ("kamino.maxfetchage", "7d"),
+            ("kamino.prune", "yes"),
+            ("kamino.refspec", "main"),
+            ("kamino.branches", "main, release/*"),
+            ("kamino.base", "origin/HEAD"),
+            ("kamino.staleafter", "180d"),
+            ("kamino.staleactivity", "yes"),
+            ("kamino.askcredentials", "true"),
+            ("kamino.storecredentials", "on"),
+            ("hooks.source", ".lefthook"),
+            ("repo.forked.refspec", "develop"),
+            (
+                "repo.forked.refspec",
+                "release/*:refs/remotes/upstream/release/*",
+            ),
+            ("repo.forked.remote", "upstream"),
+            ("repo.web.hooksource", ".husky"),
+            ("repo.web.hooksource", "scripts/git-hooks"),
+        ]));
+        assert!(problems.is_empty(), "{problems:?}");
+        let options = config.scan_options("forked");
+        assert_eq!(options.get_remote(), "upstream");
+        assert_eq!(options.get_fetch(), FetchPolicy::AllRemotes);
+        assert_eq!(options.get_fetch_timeout(), Some(Duration::from_secs(30)));
+        assert_eq!(options.get_fetch_ttl(), Some(Duration::from_secs(5400)));
+        assert!(options.get_prune());
+        assert_eq!(
+            options.get_refspecs(),
+            ["develop", "release/*:refs/remotes/upstream/release/*"]
+        );
+        assert_eq!(config.scan_options("web").get_refspecs(), ["main"]);
+        assert_eq!(
+            options.get_branches(),
+            &BranchFilter::Matching(vec![String::from("main"), String::from("release/*")])
+        );
+        assert!(config.to_string().contains(
+            "\tfetchTimeout = 30\n\
+             \tfetchesPerHost = 2\n\
+             \tfetchTtl = 90m\n\
+             \tcacheTtl = 2h\n\
+             \tmaxFetchAge = 7d\n\
+             \tprune = true\n\
+             \trefspec = main\n\
+             \tbranches = main,release/*\n\
+             \tbase = origin/HEAD\n\
+             \tstaleAfter = 180d\n\
+             \tstaleActivity = true\n\
+             \taskCredentials = true\n\
+             \tstoreCredentials = true\n"
+        ));
+        assert_eq!(options.get_hook_sources(), [".lefthook"]);
+        assert_eq!(
+            config.scan_options("web").get_hook_sources(),
+            [".husky", "scripts/git-hooks"]
+        );
+        assert!(config
+            .to_string()
+            .contains("[repo \"web\"]\n\thookSource = .husky\n\thookSource = scripts/git-hooks\n"));
+        assert_eq!(config.scan_options("other").get_remote(), "origin");
+
+        config.quick = true;
+        assert_eq!(config.scan_options("any").get_fetch(), FetchPolicy::Never);
+    }
+
+    #[test]
+    fn problems() {
+        let (_, problems) = Config::from_entries(&entries(&[
+            ("kamino.colour", "always"),
+            ("kamino.quietwhenclean", "maybe"),
+            ("kamino.fetchtimeout", "0"),
+            ("kamino.fetchttl", "1w"),
+            ("kamino.branches", " , "),
+            ("kamino.skip", "hooks,nope"),
+            ("kamino.failon", "hooks"),
+            ("scan.exclude", "old-[abc"),
+            ("scan.exclude", "archived"),
+            ("repo.archived.remote", "upstream"),
+            ("repo.other.color", "red"),
+            ("lifecycle.staledays", "ninety"),
+            ("lifecycle.archiveddays", "30"),
+            ("largefiles.threshold", "big"),
+        ]));
+        let problems: Vec<String> = problems.into_iter().map(|p| p.0).collect();
+        assert_eq!(
+            problems,
+            vec![
+                "unknown key \"kamino.colour\"",
+                "kamino.quietwhenclean: \"maybe\" is not a boolean",
+                "kamino.fetchtimeout: \"0\" is not a number of seconds",
+                "kamino.fetchttl: \"1w\" is not a duration (expected e.g. 30s, 15m, 1h or 2d)",
+                "kamino.branches: no branches given",
+                "kamino.skip: unknown check \"nope\"",
+                "scan.exclude: bad glob: unclosed character class in \"old-[abc\"",
+                "unknown key \"repo.other.color\"",
+                "lifecycle.staledays: \"ninety\" is not a number of days",
+                "largefiles.threshold: \"big\" is not a size",
+                "kamino.failOn \"hooks\" can never match because the check is skipped",
+                "lifecycle.staleDays should be less than lifecycle.archivedDays, otherwise repos are never stale",
+                "settings for repo \"archived\" have no effect because it is excluded by scan.exclude",
+            ]
+        );
+    }
+}
@@ -0,0 +1,622 @@
+use crate::{changed::DAY, lifecycle::Stage, render::Timestamps};
+use kamino_core::{FetchStats, Summary};
+use std::{fmt, path::PathBuf, str::FromStr};
+
+/// How serious a finding is.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Level {
+    /// Worth knowing about but nothing is at risk.
+    Info,
+    /// Something probably needs attention.
+    Warning,
+    /// Work is at risk of being lost.
+    Error,
+}
+
+impl Level {
+    pub const ALL: [Self; 3] = [Self::Info, Self::Warning, Self::Error];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Which check produced a finding.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Check {
+    Uncommitted,
+    Stashed,
+    StaleStash,
+    State,
+    CheckedOut,
+    Remote,
+    RemoteUrl,
+    Ahead,
+    Behind,
+    Base,
+    Fetch,
+    FetchAge,
+    RemoteHead,
+    Shallow,
+    Tags,
+    Merged,
+    Stale,
+    Grafts,
+    Lfs,
+    LargeFiles,
+    TrackedIgnored,
+    Submodules,
+    Nested,
+    Worktrees,
+    Hooks,
+    Permissions,
+    Identity,
+    Signatures,
+    PerfConfig,
+    /// Not a check itself: the repo couldn't be checked at all, so the other checks' findings are missing.
+    Broken,
+}
+
+impl Check {
+    pub const ALL: [Self; 30] = [
+        Self::Uncommitted,
+        Self::Stashed,
+        Self::StaleStash,
+        Self::State,
+        Self::CheckedOut,
+        Self::Remote,
+        Self::RemoteUrl,
+        Self::Ahead,
+        Self::Behind,
+        Self::Base,
+        Self::Fetch,
+        Self::FetchAge,
+        Self::RemoteHead,
+        Self::Shallow,
+        Self::Tags,
+        Self::Merged,
+        Self::Stale,
+        Self::Grafts,
+        Self::Lfs,
+        Self::LargeFiles,
+        Self::TrackedIgnored,
+        Self::Submodules,
+        Self::Nested,
+        Self::Worktrees,
+        Self::Hooks,
+        Self::Permissions,
+        Self::Identity,
+        Self::Signatures,
+        Self::PerfConfig,
+        Self::Broken,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Uncommitted => "uncommitted",
+            Self::Stashed => "stashed",
+            Self::StaleStash => "stale-stash",
+            Self::State => "state",
+            Self::CheckedOut => "checked-out",
+            Self::Remote => "remote",
+            Self::RemoteUrl => "remote-url",
+            Self::Ahead => "ahead",
+            Self::Behind => "behind",
+            Self::Base => "base",
+            Self::Fetch => "fetch",
+            Self::FetchAge => "fetch-age",
+            Self::RemoteHead => "remote-head",
+            Self::Shallow => "shallow",
+            Self::Tags => "tags",
+            Self::Merged => "merged",
+            Self::Stale => "stale",
+            Self::Grafts => "grafts",
+            Self::Lfs => "lfs",
+            Self::LargeFiles => "large-files",
+            Self::TrackedIgnored => "tracked-ignored",
+            Self::Submodules => "submodules",
+            Self::Nested => "nested",
+            Self::Worktrees => "worktrees",
+            Self::Hooks => "hooks",
+            Self::Permissions => "permissions",
+            Self::Identity => "identity",
+            Self::Signatures => "signatures",
+            Self::PerfConfig => "perf-config",
+            Self::Broken => "broken",
+        }
+    }
+
+    /// A sentence saying what the check looks for, e.g. to describe it as a rule in SARIF.
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Uncommitted => "Changes which haven't been committed.",
+            Self::Stashed => "Changes which were stashed.",
+            Self::StaleStash => "Stashes old enough to have been forgotten.",
+            Self::State => "A merge, rebase, cherry-pick or similar which is in progress.",
+            Self::CheckedOut => "A branch other than the default branch is checked out.",
+            Self::Remote => "No remote to compare with.",
+            Self::RemoteUrl => "A remote URL which the policy doesn't allow.",
+            Self::Ahead => "Local commits which aren't on the remote.",
+            Self::Behind => "Commits on the remote which aren't local.",
+            Self::Base => "Branches which are behind the base they are compared with.",
+            Self::Fetch => "A fetch which failed or timed out.",
+            Self::FetchAge => "A repo which hasn't been fetched for a while.",
+            Self::RemoteHead => "The remote's default branch changed.",
+            Self::Shallow => "A shallow clone, where counts are only lower bounds.",
+            Self::Tags => "Local tags which aren't on the remote.",
+            Self::Merged => "Local branches already merged into the default branch.",
+            Self::Stale => "A repo with no recent commits.",
+            Self::Grafts => "Replace refs or grafts which rewrite history locally.",
+            Self::Lfs => "Git LFS content which wasn't fetched or checked out.",
+            Self::LargeFiles => "Tracked files larger than the threshold.",
+            Self::TrackedIgnored => "Tracked files which match the ignore rules.",
+            Self::Submodules => {
+                "Submodules which are uninitialized, changed or at the wrong commit."
+            }
+            Self::Nested => "Repos nested in the working tree which aren't submodules.",
+            Self::Worktrees => "Linked worktrees with changes, or which are locked or missing.",
+            Self::Hooks => "Git hooks which don't match the in-repo or central ones, or won't run.",
+            Self::Permissions => "Files other users could change to run code as you.",
+            Self::Identity => "A commit identity which isn't set or doesn't match the policy.",
+            Self::Signatures => "Unpushed commits which aren't signed as expected.",
+            Self::PerfConfig => "Settings which would make checking for changes faster.",
+            Self::Broken => "A repo which couldn't be checked, e.g. because it is corrupt.",
+        }
+    }
+
+    /// The level reported for findings from this check.
+    pub fn level(self) -> Level {
+        match self {
+            Self::Uncommitted | Self::Remote | Self::Ahead | Self::Broken => Level::Error,
+            Self::Stashed
+            | Self::StaleStash
+            | Self::State
+            | Self::RemoteUrl
+            | Self::Fetch
+            | Self::FetchAge
+            | Self::RemoteHead
+            | Self::Shallow
+            | Self::Tags
+            | Self::Grafts
+            | Self::Lfs
+            | Self::LargeFiles
+            | Self::TrackedIgnored
+            | Self::Submodules
+            | Self::Nested
+            | Self::Worktrees
+            | Self::Hooks
+            | Self::Permissions
+            | Self::Identity
+            | Self::Signatures => Level::Warning,
+            Self::Behind
+            | Self::Base
+            | Self::CheckedOut
+            | Self::Merged
+            | Self::Stale
+            | Self::PerfConfig => Level::Info,
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Part of a finding's message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Segment {
+    /// Plain text.
+    Text(String),
+    /// The name of something (a branch, tag, file...), with a URL for it if one is known.
+    Ref { text: String, url: Option<String> },
+    /// A time, in seconds since the Unix epoch, which renderers show in the chosen time zone and relative to now.
+    Time(i64),
+}
+
+/// The text of a finding, split up so that renderers can treat references specially.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Message(pub Vec<Segment>);
+
+impl Message {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.0.push(Segment::Text(text.into()));
+        self
+    }
+
+    #[must_use]
+    pub fn reference(mut self, text: impl Into<String>, url: Option<String>) -> Self {
+        self.0.push(Segment::Ref {
+            text: text.into(),
+            url,
+        });
+        self
+    }
+
+    #[must_use]
+    pub fn time(mut self, time: i64) -> Self {
+        self.0.push(Segment::Time(time));
+        self
+    }
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Self::new().text(text)
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Self::new().text(text)
+    }
+}
+
+/// Formats the message as plain text, with times in UTC.
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in &self.0 {
+            match segment {
+                Segment::Text(text) | Segment::Ref { text, .. } => f.write_str(text)?,
+                Segment::Time(time) => f.write_str(&Timestamps::default().iso(*time))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Something kamino can fix automatically, if asked to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Fix {
+    /// Copy hooks from `.githooks` into `.git/hooks`.
+    Hooks,
+    /// Fast-forward local branches which are strictly behind their upstream.
+    Pull,
+    /// Push local branches which are strictly ahead of their upstream.
+    Push,
+    /// Delete remote-tracking branches which no longer exist on the remote.
+    Prune,
+    /// Delete local branches which are merged and whose upstream branch was deleted.
+    Branches,
+    /// Fetch the full history of shallow clones.
+    Unshallow,
+    /// Stop everyone being able to write to the git directory and hooks.
+    Permissions,
+    /// Turn on git settings which make status faster, in repos where it is slow.
+    PerfConfig,
+}
+
+impl Fix {
+    pub const ALL: [Self; 8] = [
+        Self::Hooks,
+        Self::Pull,
+        Self::Push,
+        Self::Prune,
+        Self::Branches,
+        Self::Unshallow,
+        Self::Permissions,
+        Self::PerfConfig,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Hooks => "hooks",
+            Self::Pull => "pull",
+            Self::Push => "push",
+            Self::Prune => "prune",
+            Self::Branches => "branches",
+            Self::Unshallow => "unshallow",
+            Self::Permissions => "permissions",
+            Self::PerfConfig => "perf-config",
+        }
+    }
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Fix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|fix| fix.as_str() == s)
+            .ok_or_else(|| {
+                let valid: Vec<&str> = Self::ALL.iter().map(|fix| fix.as_str()).collect();
+                format!(
+                    "unknown fix \"{s}\" (expected one of: {})",
+                    valid.join(", ")
+                )
+            })
+    }
+}
+
+/// What came of a fix.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    /// The change was made.
+    Applied,
+    /// With `--dry-run`, the change would have been made.
+    Planned,
+    /// Making the change failed, with the error.
+    Failed(String),
+}
+
+impl Outcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Applied => "applied",
+            Self::Planned => "planned",
+            Self::Failed(_) => "failed",
+        }
+    }
+
+    /// The error, if the fix failed.
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            Self::Failed(error) => Some(error),
+            Self::Applied | Self::Planned => None,
+        }
+    }
+}
+
+/// A fix made, or attempted, in a repo.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RemediationResult {
+    pub action: Fix,
+    pub outcome: Outcome,
+    /// What was changed, e.g. "Pushed branch main, which was ahead of origin/main by 2 commits".
+    pub message: Message,
+}
+
+impl RemediationResult {
+    /// A fix which was made, or only planned if `dry_run` is set.
+    pub fn new(action: Fix, dry_run: bool, message: impl Into<Message>) -> Self {
+        Self {
+            action,
+            outcome: if dry_run {
+                Outcome::Planned
+            } else {
+                Outcome::Applied
+            },
+            message: message.into(),
+        }
+    }
+
+    /// A fix which was attempted and failed. The message says what was attempted.
+    pub fn failed(action: Fix, message: impl Into<Message>, error: impl fmt::Display) -> Self {
+        Self {
+            action,
+            outcome: Outcome::Failed(error.to_string()),
+            message: message.into(),
+        }
+    }
+
+    /// The message, followed by the error if the fix failed.
+    pub fn describe(&self) -> Message {
+        match &self.outcome {
+            Outcome::Failed(error) => self.message.clone().text(format!(": {error}")),
+            Outcome::Applied | Outcome::Planned => self.message.clone(),
+        }
+    }
+}
+
+/// A single problem found in a repo.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Finding {
+    pub check: Check,
+    pub level: Level,
+    pub message: Message,
+    /// When the finding was first seen, in seconds since the Unix epoch, if an earlier scan found it too.
+    pub first_seen: Option<i64>,
+    /// Lines printed under the message, e.g. a diff. Only the text output has them, and they aren't recorded.
+    pub detail: Vec<String>,
+}
+
+impl Finding {
+    pub fn new(check: Check, message: impl Into<Message>) -> Self {
+        Self {
+            check,
+            level: check.level(),
+            message: message.into(),
+            first_seen: None,
+            detail: Vec::new(),
+        }
+    }
+
+    /// The message, followed by when the finding was first seen if an earlier scan found it too.
+    pub fn describe(&self) -> Message {
+        match self.first_seen {
+            Some(time) => self.message.clone().text(", first seen ").time(time),
+            None => self.message.clone(),
+        }
+    }
+
+    /// Raise the finding's level once for every `days` it has been around, up to an error.
+    pub fn escalate(&mut self, days: u32, now: i64) {
+        let first_seen = match self.first_seen {
+            Some(first_seen) if days > 0 => first_seen,
+            _ => return,
+        };
+        let steps = (now - first_seen) / (i64::from(days) * DAY);
+        let index = Level::ALL
+            .iter()
+            .position(|&l| l == self.level)
+            .unwrap_or(0);
+        let raised = index.saturating_add(usize::try_from(steps).unwrap_or(0));
+        self.level = Level::ALL[raised.min(Level::ALL.len() - 1)];
+    }
+
+    /// Whether the finding is more urgent than its check usually is, because it has been around for a while.
+    pub fn is_escalated(&self) -> bool {
+        self.level > self.check.level()
+    }
+}
+
+/// All the findings for a single repo.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RepoReport {
+    /// The repo's directory name.
+    pub name: String,
+    /// Path to the repo.
+    pub path: PathBuf,
+    pub findings: Vec<Finding>,
+    /// What was fixed automatically, or attempted to be.
+    pub fixes: Vec<RemediationResult>,
+    /// Lifecycle stage, or None if the repo has no activity at all.
+    pub stage: Option<Stage>,
+    /// Counts of what was found, to add up over the whole scan.
+    pub summary: Summary,
+    /// What was fetched from each remote, to see which repos dominate scan time and bandwidth.
+    pub fetches: Vec<FetchStats>,
+}
+
+impl RepoReport {
+    /// Whether the repo couldn't be checked, so that its report has just the error.
+    pub fn is_broken(&self) -> bool {
+        self.findings
+            .iter()
+            .any(|finding| finding.check == Check::Broken)
+    }
+
+    /// A report on `/repos/<name>` with just these findings, for tests to fill in the rest of as they need.
+    #[cfg(test)]
+    pub fn for_test(name: &str, findings: Vec<Finding>) -> Self {
+        Self {
+            name: name.into(),
+            path: PathBuf::from("/repos").join(name),
+            findings,
+            fixes: Vec::new(),
+            stage: None,
+            summary: Summary::default(),
+            fetches: Vec::new(),
+        }
+    }
+}
+
+/// Condition given to `--fail-on` which makes the program exit with a failure code.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FailOn {
+    /// Any finding at this level or above.
+    Level(Level),
+    /// Any finding from this check.
+    Check(Check),
+}
+
+impl FailOn {
+    pub fn matches(self, finding: &Finding) -> bool {
+        match self {
+            Self::Level(level) => finding.level >= level,
+            Self::Check(check) => finding.check == check,
+        }
+    }
+}
+
+impl fmt::Display for FailOn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Level(level) => level.fmt(f),
+            Self::Check(check) => check.fmt(f),
+        }
+    }
+}
+
+impl FromStr for FailOn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(level) = Level::ALL.into_iter().find(|l| l.as_str() == s) {
+            Ok(Self::Level(level))
+        } else if let Some(check) = Check::ALL.into_iter().find(|c| c.as_str() == s) {
+            Ok(Self::Check(check))
+        } else {
+            let valid: Vec<&str> = Level::ALL
+                .iter()
+                .map(|l| l.as_str())
+                .chain(Check::ALL.iter().map(|c| c.as_str()))
+                .collect();
+            Err(format!(
+                "unknown level or check \"{s}\" (expected one of: {})",
+                valid.join(", ")
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escalate() {
+        let mut behind = Finding::new(Check::Behind, "behind");
+        behind.escalate(7, 100 * DAY);
+        assert_eq!(behind.level, Level::Info);
+
+        behind.first_seen = Some(0);
+        behind.escalate(7, 6 * DAY);
+        assert_eq!(behind.level, Level::Info);
+        assert!(!behind.is_escalated());
+        behind.escalate(7, 7 * DAY);
+        assert_eq!(behind.level, Level::Warning);
+        assert!(behind.is_escalated());
+
+        let mut stashed = Finding::new(Check::Stashed, "stashed");
+        stashed.first_seen = Some(0);
+        stashed.escalate(7, 100 * DAY);
+        assert_eq!(stashed.level, Level::Error);
+    }
+
+    #[test]
+    fn fail_on() {
+        assert_eq!("warning".parse(), Ok(FailOn::Level(Level::Warning)));
+        assert_eq!("hooks".parse(), Ok(FailOn::Check(Check::Hooks)));
+        assert!("bogus".parse::<FailOn>().is_err());
+
+        let behind = Finding::new(Check::Behind, "behind");
+        let ahead = Finding::new(Check::Ahead, "ahead");
+        assert!(FailOn::Level(Level::Info).matches(&behind));
+        assert!(!FailOn::Level(Level::Warning).matches(&behind));
+        assert!(FailOn::Level(Level::Warning).matches(&ahead));
+        assert!(FailOn::Check(Check::Ahead).matches(&ahead));
+        assert!(!FailOn::Check(Check::Ahead).matches(&behind));
+    }
+
+    #[test]
+    fn remediation() {
+        let pushed = RemediationResult::new(Fix::Push, false, "Pushed branch main");
+        assert_eq!(pushed.outcome, Outcome::Applied);
+        assert_eq!(pushed.describe().to_string(), "Pushed branch main");
+        let planned = RemediationResult::new(Fix::Push, true, "Would push branch main");
+        assert_eq!(planned.outcome.as_str(), "planned");
+        assert_eq!(planned.outcome.error(), None);
+
+        let failed = RemediationResult::failed(Fix::Pull, "Fast-forward branch main", "diverged");
+        assert_eq!(failed.outcome.as_str(), "failed");
+        assert_eq!(failed.outcome.error(), Some("diverged"));
+        assert_eq!(
+            failed.describe().to_string(),
+            "Fast-forward branch main: diverged"
+        );
+    }
+}
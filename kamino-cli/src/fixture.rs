@@ -0,0 +1,457 @@
+use crate::{
+    findings::{
+        Check, Finding, Fix, Level, Message, Outcome, RemediationResult, RepoReport, Segment,
+    },
+    lifecycle::Stage,
+};
+use kamino_core::{FetchStats, Summary};
+use std::{fmt::Write, fs, io, path::Path, time::Duration};
+
+/// Error type for [`load()`].
+#[derive(thiserror::Error, Debug)]
+pub enum FixtureError {
+    #[error("failed to read fixture")]
+    Io(#[from] io::Error),
+    #[error("line {line}: {message}")]
+    Parse { line: usize, message: String },
+}
+
+/// Save the reports from a scan so they can be replayed later with [`load()`].
+///
+/// One line per record, with tab-separated fields:
+///
+/// ```text
+/// repo     <name>  <path>  <stage or ->
+/// summary  <count>=<n>...
+/// finding  <check> [F<first seen>] [L<level>] <segment>...
+/// fix      <action> <outcome> [<error>] <segment>...
+/// fetch    <remote> <bytes> <objects> <milliseconds>
+/// ```
+///
+/// Summaries, findings, fixes and fetches belong to the repo above them. A fix's outcome is `applied`, `planned` or
+/// `failed`, and only a failed one has the error. A finding has the time it was first seen if that is known, and its
+/// level if it was escalated from the check's usual one. Summary counts are named after the fields of [`Summary`]. Each
+/// message segment is a field starting with `T` for text, `R` for a reference or `D` for a time in seconds since the
+/// Unix epoch, and a reference's URL follows in a field starting with `U`. Tabs, newlines and backslashes in values are
+/// escaped with a backslash.
+pub fn save(path: &Path, reports: &[RepoReport]) -> io::Result<()> {
+    let mut text = String::from("# Scan recorded by kamino, replay with `kamino --replay`\n");
+    text.push_str(&to_text(reports));
+    crate::report::write(path, &text)
+}
+
+/// The records for the reports, as [`save()`] writes them, for files which keep reports along with other things.
+pub fn to_text(reports: &[RepoReport]) -> String {
+    let mut text = String::new();
+    for report in reports {
+        let stage = report.stage.map_or("-", Stage::as_str);
+        let _ = writeln!(
+            text,
+            "repo\t{}\t{}\t{stage}",
+            escape(&report.name),
+            escape(&report.path.to_string_lossy())
+        );
+        let summary = &report.summary;
+        let _ = writeln!(
+            text,
+            "summary\trepos={}\tclean={}\tuncommitted={}\tstashes={}\tahead={}\tbehind={}\thook_problems={}\tat_risk={}",
+            summary.repos,
+            summary.clean,
+            summary.uncommitted,
+            summary.stashes,
+            summary.ahead,
+            summary.behind,
+            summary.hook_problems,
+            summary.at_risk
+        );
+        for finding in &report.findings {
+            let first_seen = match finding.first_seen {
+                Some(time) => format!("\tF{time}"),
+                None => String::new(),
+            };
+            let level = if finding.is_escalated() {
+                format!("\tL{}", finding.level)
+            } else {
+                String::new()
+            };
+            let _ = writeln!(
+                text,
+                "finding\t{}{first_seen}{level}{}",
+                finding.check,
+                message_fields(&finding.message)
+            );
+        }
+        for fix in &report.fixes {
+            let error = match fix.outcome.error() {
+                Some(error) => format!("\t{}", escape(error)),
+                None => String::new(),
+            };
+            let _ = writeln!(
+                text,
+                "fix\t{}\t{}{error}{}",
+                fix.action,
+                fix.outcome.as_str(),
+                message_fields(&fix.message)
+            );
+        }
+        for fetch in &report.fetches {
+            let _ = writeln!(
+                text,
+                "fetch\t{}\t{}\t{}\t{}",
+                escape(&fetch.remote),
+                fetch.received_bytes,
+                fetch.received_objects,
+                fetch.duration.as_millis()
+            );
+        }
+    }
+    text
+}
+
+/// Load reports saved with [`save()`].
+///
+/// # Errors
+///
+/// See [`FixtureError`].
+pub fn load(path: &Path) -> Result<Vec<RepoReport>, FixtureError> {
+    parse(&fs::read_to_string(path)?)
+}
+
+/// Parse records written by [`to_text()`], where lines are numbered from the start of `text`.
+///
+/// # Errors
+///
+/// See [`FixtureError`].
+pub fn parse(text: &str) -> Result<Vec<RepoReport>, FixtureError> {
+    let mut reports: Vec<RepoReport> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let error = |message: String| FixtureError::Parse {
+            line: i + 1,
+            message,
+        };
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<String> = line.split('\t').map(unescape).collect();
+        match fields[0].as_str() {
+            "repo" => {
+                let (name, path, stage) = match fields.as_slice() {
+                    [_, name, path, stage] => (name, path, stage),
+                    _ => return Err(error("expected repo name, path and stage".into())),
+                };
+                let stage = match stage.as_str() {
+                    "-" => None,
+                    s => Some(
+                        Stage::ALL
+                            .into_iter()
+                            .find(|stage| stage.as_str() == s)
+                            .ok_or_else(|| error(format!("unknown stage \"{s}\"")))?,
+                    ),
+                };
+                reports.push(RepoReport {
+                    name: name.clone(),
+                    path: path.into(),
+                    findings: Vec::new(),
+                    fixes: Vec::new(),
+                    stage,
+                    fetches: Vec::new(),
+                    summary: Summary::default(),
+                });
+            }
+            "summary" => {
+                let report = reports
+                    .last_mut()
+                    .ok_or_else(|| error("summary before any repo".into()))?;
+                for field in &fields[1..] {
+                    let bad = || error(format!("bad summary count \"{field}\""));
+                    let (name, value) = field.split_once('=').ok_or_else(bad)?;
+                    let summary = &mut report.summary;
+                    if name == "at_risk" {
+                        summary.at_risk = value.parse().map_err(|_| bad())?;
+                        continue;
+                    }
+                    let count = match name {
+                        "repos" => &mut summary.repos,
+                        "clean" => &mut summary.clean,
+                        "uncommitted" => &mut summary.uncommitted,
+                        "stashes" => &mut summary.stashes,
+                        "ahead" => &mut summary.ahead,
+                        "behind" => &mut summary.behind,
+                        "hook_problems" => &mut summary.hook_problems,
+                        _ => return Err(error(format!("unknown summary count \"{name}\""))),
+                    };
+                    *count = value.parse().map_err(|_| bad())?;
+                }
+            }
+            kind @ ("finding" | "fix") => {
+                let report = reports
+                    .last_mut()
+                    .ok_or_else(|| error(format!("{kind} before any repo")))?;
+                if kind == "fix" {
+                    let action = fields.get(1).map(String::as_str).unwrap_or_default();
+                    let action = action
+                        .parse::<Fix>()
+                        .map_err(|_| error(format!("unknown fix \"{action}\"")))?;
+                    let (outcome, rest) = match fields.get(2).map(String::as_str) {
+                        Some("applied") => (Outcome::Applied, &fields[3..]),
+                        Some("planned") => (Outcome::Planned, &fields[3..]),
+                        Some("failed") if fields.len() > 3 => {
+                            (Outcome::Failed(fields[3].clone()), &fields[4..])
+                        }
+                        other => {
+                            let other = other.unwrap_or_default();
+                            return Err(error(format!("bad fix outcome \"{other}\"")));
+                        }
+                    };
+                    report.fixes.push(RemediationResult {
+                        action,
+                        outcome,
+                        message: parse_message(rest).map_err(error)?,
+                    });
+                } else {
+                    let name = fields.get(1).map(String::as_str).unwrap_or_default();
+                    let check = Check::ALL
+                        .into_iter()
+                        .find(|check| check.as_str() == name)
+                        .ok_or_else(|| error(format!("unknown check \"{name}\"")))?;
+                    let mut finding = Finding::new(check, Message::new());
+                    let mut rest = &fields[2..];
+                    while let Some(field) = rest.first() {
+                        if let Some(time) = field.strip_prefix('F') {
+                            let bad = || error(format!("bad first seen time \"{time}\""));
+                            finding.first_seen = Some(time.parse().map_err(|_| bad())?);
+                        } else if let Some(level) = field.strip_prefix('L') {
+                            finding.level = Level::ALL
+                                .into_iter()
+                                .find(|l| l.as_str() == level)
+                                .ok_or_else(|| error(format!("unknown level \"{level}\"")))?;
+                        } else {
+                            break;
+                        }
+                        rest = &rest[1..];
+                    }
+                    finding.message = parse_message(rest).map_err(error)?;
+                    report.findings.push(finding);
+                }
+            }
+            "fetch" => {
+                let report = reports
+                    .last_mut()
+                    .ok_or_else(|| error("fetch before any repo".into()))?;
+                let bad = || error("expected fetch remote, bytes, objects and milliseconds".into());
+                let (remote, bytes, objects, millis) = match fields.as_slice() {
+                    [_, remote, bytes, objects, millis] => (remote, bytes, objects, millis),
+                    _ => return Err(bad()),
+                };
+                report.fetches.push(FetchStats {
+                    remote: remote.clone(),
+                    received_bytes: bytes.parse().map_err(|_| bad())?,
+                    received_objects: objects.parse().map_err(|_| bad())?,
+                    duration: Duration::from_millis(millis.parse().map_err(|_| bad())?),
+                });
+            }
+            other => return Err(error(format!("unknown record \"{other}\""))),
+        }
+    }
+    Ok(reports)
+}
+
+// The fields for a message's segments, each preceded by a tab.
+fn message_fields(message: &Message) -> String {
+    let mut fields = String::new();
+    for segment in &message.0 {
+        match segment {
+            Segment::Text(text) => {
+                let _ = write!(fields, "\tT{}", escape(text));
+            }
+            Segment::Ref { text, url } => {
+                let _ = write!(fields, "\tR{}", escape(text));
+                if let Some(url) = url {
+                    let _ = write!(fields, "\tU{}", escape(url));
+                }
+            }
+            Segment::Time(time) => {
+                let _ = write!(fields, "\tD{time}");
+            }
+        }
+    }
+    fields
+}
+
+fn parse_message(fields: &[String]) -> Result<Message, String> {
+    let mut message = Message::new();
+    for field in fields {
+        let (kind, value) = field.split_at(field.chars().next().map_or(0, char::len_utf8));
+        match kind {
+            "T" => message = message.text(value),
+            "R" => message = message.reference(value, None),
+            "D" => match value.parse() {
+                Ok(time) => message = message.time(time),
+                Err(_) => return Err(format!("bad time \"{value}\"")),
+            },
+            "U" => match message.0.last_mut() {
+                Some(Segment::Ref { url, .. }) if url.is_none() => *url = Some(value.to_owned()),
+                _ => return Err("URL without a reference".into()),
+            },
+            _ => return Err(format!("bad message segment \"{field}\"")),
+        }
+    }
+    Ok(message)
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('t') => out.push('\t'),
+                Some('n') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn save_load() {
+        let mut reports = vec![
+            RepoReport {
+                name: "kamino".into(),
+                path: "./kamino".into(),
+                findings: vec![
+                    Finding::new(
+                        Check::Ahead,
+                        Message::new()
+                            .text("Branch ")
+                            .reference("main", None)
+                            .text(" is ahead of ")
+                            .reference(
+                                "origin/main",
+                                Some("https://github.com/schteve/kamino/tree/main".into()),
+                            )
+                            .text(" by 1 commits"),
+                    ),
+                    Finding::new(Check::Hooks, "Odd\tname\\with\nbreaks"),
+                    Finding::new(
+                        Check::Stashed,
+                        Message::new()
+                            .text("Has 1 stashed changes, the oldest from ")
+                            .time(-86_400),
+                    ),
+                ],
+                fixes: vec![
+                    RemediationResult::new(
+                        Fix::Hooks,
+                        false,
+                        Message::new()
+                            .text("Copied hook ")
+                            .reference("pre-commit", None),
+                    ),
+                    RemediationResult::failed(
+                        Fix::Push,
+                        "Push branch main",
+                        "failed to push:\tno\\network",
+                    ),
+                ],
+                stage: Some(Stage::Stale),
+                fetches: vec![FetchStats {
+                    remote: "origin".into(),
+                    received_bytes: 4096,
+                    received_objects: 7,
+                    duration: Duration::from_millis(2300),
+                }],
+                summary: Summary {
+                    repos: 1,
+                    ahead: 1,
+                    hook_problems: 1,
+                    at_risk: 1_300_000_000,
+                    ..Summary::default()
+                },
+            },
+            RepoReport {
+                path: "./empty".into(),
+                summary: Summary {
+                    repos: 1,
+                    clean: 1,
+                    ..Summary::default()
+                },
+                ..RepoReport::for_test("empty", Vec::new())
+            },
+        ];
+        let stashed = &mut reports[0].findings[2];
+        stashed.first_seen = Some(-172_800);
+        stashed.level = Level::Error;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("scan.fixture");
+        save(&path, &reports).unwrap();
+        assert_eq!(load(&path).unwrap(), reports);
+    }
+
+    #[test]
+    fn errors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("scan.fixture");
+        let cases = [
+            ("finding\tahead\tTx\n", "line 1: finding before any repo"),
+            (
+                "repo\ta\t./a\t-\nfinding\tbogus\n",
+                "line 2: unknown check \"bogus\"",
+            ),
+            (
+                "repo\ta\t./a\t-\nfinding\tahead\tLurgent\tTx\n",
+                "line 2: unknown level \"urgent\"",
+            ),
+            (
+                "repo\ta\t./a\tancient\n",
+                "line 1: unknown stage \"ancient\"",
+            ),
+            (
+                "repo\ta\t./a\t-\nfix\tpull\tapplied\tUhttps://x\n",
+                "line 2: URL without a reference",
+            ),
+            (
+                "repo\ta\t./a\t-\nfix\tTCopied hook\n",
+                "line 2: unknown fix \"TCopied hook\"",
+            ),
+            (
+                "repo\ta\t./a\t-\nfix\thooks\tfailed\n",
+                "line 2: bad fix outcome \"failed\"",
+            ),
+            (
+                "repo\ta\t./a\t-\nfinding\tstashed\tDyesterday\n",
+                "line 2: bad time \"yesterday\"",
+            ),
+            ("repo\ta\n", "line 1: expected repo name, path and stage"),
+            (
+                "repo\ta\t./a\t-\nfetch\torigin\t10\tmany\t5\n",
+                "line 2: expected fetch remote, bytes, objects and milliseconds",
+            ),
+            (
+                "repo\ta\t./a\t-\nsummary\tstashes=x\n",
+                "line 2: bad summary count \"stashes=x\"",
+            ),
+        ];
+        for (text, expected) in cases {
+            fs::write(&path, text).unwrap();
+            assert_eq!(load(&path).unwrap_err().to_string(), expected);
+        }
+    }
+}
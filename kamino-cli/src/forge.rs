@@ -0,0 +1,88 @@
+/// Web address of a repo hosted on a forge such as GitHub or GitLab, used to link to things on the remote.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Forge {
+    kind: ForgeKind,
+    base: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ForgeKind {
+    GitHub,
+    GitLab,
+}
+
+impl Forge {
+    /// Work out the forge from a remote URL, in either the `https://host/owner/repo.git` or
+    /// `git@host:owner/repo.git` form. Returns `None` for hosts that aren't recognized.
+    pub fn from_remote_url(url: &str) -> Option<Self> {
+        let without_scheme = url
+            .split_once("://")
+            .map_or(url, |(_, rest)| rest)
+            .trim_end_matches('/');
+        let without_user = without_scheme
+            .split_once('@')
+            .map_or(without_scheme, |(_, rest)| rest);
+        // scp-like syntax separates the host with ':', otherwise it's the first '/'
+        let (host, path) = without_user.split_once([':', '/'])?;
+        let host = host.to_lowercase();
+        let path = path.trim_end_matches(".git").trim_matches('/');
+        if path.is_empty() || path.contains(':') {
+            return None;
+        }
+
+        let kind = if host == "github.com" {
+            ForgeKind::GitHub
+        } else if host.split('.').any(|part| part == "gitlab") {
+            ForgeKind::GitLab
+        } else {
+            return None;
+        };
+
+        Some(Self {
+            kind,
+            base: format!("https://{host}/{path}"),
+        })
+    }
+
+    /// Web page for a branch.
+    pub fn branch_url(&self, branch: &str) -> String {
+        match self.kind {
+            ForgeKind::GitHub => format!("{}/tree/{branch}", self.base),
+            ForgeKind::GitLab => format!("{}/-/tree/{branch}", self.base),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn remote_urls() {
+        for url in [
+            "https://github.com/schteve/kamino.git",
+            "https://github.com/schteve/kamino",
+            "git@github.com:schteve/kamino.git",
+            "ssh://git@github.com/schteve/kamino.git",
+        ] {
+            let forge = Forge::from_remote_url(url).unwrap();
+            assert_eq!(
+                forge.branch_url("main"),
+                "https://github.com/schteve/kamino/tree/main",
+                "{url}"
+            );
+        }
+
+        let forge = Forge::from_remote_url("git@gitlab.example.com:group/sub/repo.git").unwrap();
+        assert_eq!(
+            forge.branch_url("dev"),
+            "https://gitlab.example.com/group/sub/repo/-/tree/dev"
+        );
+
+        assert_eq!(Forge::from_remote_url("/home/me/repos/kamino"), None);
+        assert_eq!(
+            Forge::from_remote_url("https://example.com/kamino.git"),
+            None
+        );
+    }
+}
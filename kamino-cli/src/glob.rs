@@ -0,0 +1,118 @@
+use std::{fmt, str::FromStr};
+
+/// A shell-style wildcard pattern matched against a whole string, such as a directory name or a remote URL.
+///
+/// Supports `*` (any run of characters, including `/`), `?` (any one character), `[...]` / `[!...]` character classes
+/// including ranges such as `[a-z]`, and `\` to match the next character as is. Matching is done by
+/// [`kamino_core::wildmatch()`] with `*` crossing `/`, unlike the branch patterns of `--branches`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Glob {
+    pattern: String,
+}
+
+/// Error returned when a glob pattern is malformed.
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+pub enum GlobError {
+    /// The pattern is empty.
+    #[error("pattern is empty")]
+    Empty,
+    /// A `[` was never closed.
+    #[error("unclosed character class in \"{0}\"")]
+    UnclosedClass(String),
+    /// A character class has nothing in it.
+    #[error("empty character class in \"{0}\"")]
+    EmptyClass(String),
+}
+
+impl Glob {
+    /// Check whether the whole of `text` matches this pattern.
+    pub fn matches(&self, text: &str) -> bool {
+        kamino_core::wildmatch(&self.pattern, text, true)
+    }
+}
+
+impl FromStr for Glob {
+    type Err = GlobError;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        if pattern.is_empty() {
+            return Err(GlobError::Empty);
+        }
+
+        // Only character classes can be malformed
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '[' => {
+                    let class = chars.as_str();
+                    let class = class.strip_prefix(['!', '^']).unwrap_or(class);
+                    match class.find(']') {
+                        None => return Err(GlobError::UnclosedClass(pattern.to_owned())),
+                        Some(0) => return Err(GlobError::EmptyClass(pattern.to_owned())),
+                        Some(end) => chars = class[end + 1..].chars(),
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            pattern: pattern.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn glob(s: &str) -> Glob {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn matching() {
+        assert!(glob("kamino").matches("kamino"));
+        assert!(!glob("kamino").matches("kamino2"));
+        assert!(glob("*").matches(""));
+        assert!(glob("old-*").matches("old-stuff"));
+        assert!(!glob("old-*").matches("new-stuff"));
+        assert!(glob("*-archive").matches("x-archive"));
+        assert!(glob("repo?").matches("repo1"));
+        assert!(!glob("repo?").matches("repo"));
+        assert!(glob("repo[0-9]").matches("repo7"));
+        assert!(!glob("repo[!0-9]").matches("repo7"));
+        assert!(glob("repo[ab-]").matches("repo-"));
+        assert!(glob("https://github.com/*").matches("https://github.com/schteve/kamino"));
+        assert!(glob("a?c").matches("a/c"));
+        assert!(glob("repo\\*").matches("repo*"));
+        assert!(!glob("repo\\*").matches("repo1"));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!("".parse::<Glob>(), Err(GlobError::Empty));
+        assert_eq!(
+            "repo[0-9".parse::<Glob>(),
+            Err(GlobError::UnclosedClass("repo[0-9".into()))
+        );
+        assert_eq!(
+            "repo[]".parse::<Glob>(),
+            Err(GlobError::EmptyClass("repo[]".into()))
+        );
+        assert_eq!(
+            "repo[!]".parse::<Glob>(),
+            Err(GlobError::EmptyClass("repo[!]".into()))
+        );
+        assert!("repo[0-9]-[a-z]".parse::<Glob>().is_ok());
+    }
+}
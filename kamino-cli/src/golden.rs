@@ -0,0 +1,139 @@
+use crate::{
+    fixture,
+    output::Output,
+    print_report, print_summary,
+    render::{Renderer, Timestamps},
+    report::{self, Format},
+    zone::TimeZone,
+};
+use std::{env, fs, path::Path};
+
+/// Where the scenarios and their expected output are kept, relative to the crate.
+const GOLDEN_DIR: &str = "testdata/golden";
+
+/// Set this to write the output of every scenario as the new expected output, instead of comparing with it.
+const BLESS_VAR: &str = "KAMINO_BLESS";
+
+/// Title given to documents.
+const TITLE: &str = "Kamino report for /repos";
+
+// Times are relative to 2024-03-01T14:05:00Z, in a zone with summer time, so the output doesn't depend on when or
+// where the tests run.
+fn timestamps() -> Timestamps {
+    let zone = TimeZone::from_rule("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+    Timestamps::new(zone, 1_709_301_900)
+}
+
+// Every output a scenario is rendered to, by file extension.
+fn outputs(scenario: &Path) -> Vec<(&'static str, String)> {
+    let reports = fixture::load(scenario).unwrap();
+    let timestamps = timestamps();
+
+    let mut text = Output::capture(Renderer::with_timestamps(timestamps.clone()));
+    for report in &reports {
+        print_report(report, true, &mut text);
+    }
+    print_summary(&reports, &mut text);
+
+    #[allow(unused_mut)]
+    let mut outputs = vec![
+        ("txt", text.into_captured()),
+        (
+            "md",
+            report::render(Format::Markdown, TITLE, &reports, &timestamps),
+        ),
+        (
+            "html",
+            report::render(Format::Html, TITLE, &reports, &timestamps),
+        ),
+        (
+            "csv",
+            report::render(Format::Csv, TITLE, &reports, &timestamps),
+        ),
+        (
+            "ndjson",
+            report::render(Format::Ndjson, TITLE, &reports, &timestamps),
+        ),
+        (
+            "sarif",
+            // Without kamino's version, so releasing doesn't change the expected output
+            report::render(Format::Sarif, TITLE, &reports, &timestamps).replace(
+                concat!(
+                    "\"name\":\"kamino\",\"version\":\"",
+                    env!("CARGO_PKG_VERSION"),
+                    "\""
+                ),
+                "\"name\":\"kamino\",\"version\":\"VERSION\"",
+            ),
+        ),
+        (
+            "junit.xml",
+            report::render(Format::Junit, TITLE, &reports, &timestamps),
+        ),
+        (
+            "github",
+            report::render(Format::Github, TITLE, &reports, &timestamps),
+        ),
+    ];
+    #[cfg(feature = "webhook")]
+    outputs.push((
+        "json",
+        crate::webhook::payload(TITLE, &reports, &timestamps).to_string() + "\n",
+    ));
+    outputs
+}
+
+// The first line where two texts differ, to say what changed.
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.split_inclusive('\n');
+    let mut actual_lines = actual.split_inclusive('\n');
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(e), Some(a)) if e == a => continue,
+            (e, a) => return format!("line {line}: expected {e:?}, got {a:?}"),
+        }
+    }
+    unreachable!()
+}
+
+/// Render every scenario (a scan recorded in `testdata/golden/<scenario>.fixture`) in every output format, and
+/// compare with the expected output in `testdata/golden/<scenario>.<extension>`. After changing the output on
+/// purpose, run with `KAMINO_BLESS=1` to write it as the expected output, then review the diff before committing.
+/// JSON output is only checked with the `webhook` feature, and SARIF output has `VERSION` for kamino's version.
+#[test]
+fn golden() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDEN_DIR);
+    let bless = env::var_os(BLESS_VAR).is_some();
+    let mut scenarios: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "fixture"))
+        .collect();
+    scenarios.sort();
+    assert!(!scenarios.is_empty(), "no scenarios in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for scenario in &scenarios {
+        for (extension, actual) in outputs(scenario) {
+            let path = scenario.with_extension(extension);
+            if bless {
+                fs::write(&path, actual).unwrap();
+                continue;
+            }
+            match fs::read_to_string(&path) {
+                Ok(expected) if expected == actual => (),
+                Ok(expected) => failures.push(format!(
+                    "{}: {}",
+                    path.display(),
+                    first_difference(&expected, &actual)
+                )),
+                Err(e) => failures.push(format!("{}: {e}", path.display())),
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "output doesn't match (run with {BLESS_VAR}=1 to update it):\n{}",
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,167 @@
+use crate::findings::{Finding, RepoReport, Segment};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Name of the file kept in the scanned directory with when each finding was first seen.
+pub const HISTORY_FILENAME: &str = ".kaminohistory";
+
+// A finding is the same one from scan to scan if it is in the same repo, from the same check and about the same
+// thing (the first reference in its message), since the rest of the message changes with counts and so on.
+type Key = (PathBuf, String, String);
+
+/// When each finding still present was first seen, so that long-standing ones can be pointed out.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct History {
+    first_seen: BTreeMap<Key, i64>,
+}
+
+impl History {
+    /// Read the history saved by [`History::save()`], or an empty one if there isn't one yet.
+    ///
+    /// One line per finding, with tab-separated fields: the time it was first seen in seconds since the Unix
+    /// epoch, the check, what the finding is about and last the repo's path, since it may have tabs of its own.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        let mut first_seen = BTreeMap::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: expected time, check, subject and path", i + 1),
+                )
+            };
+            let (time, check, subject, path) = match line.splitn(4, '\t').collect::<Vec<_>>()[..] {
+                [time, check, subject, path] => (time, check, subject, path),
+                _ => return Err(bad()),
+            };
+            let time = time.parse().map_err(|_| bad())?;
+            first_seen.insert((path.into(), check.to_owned(), subject.to_owned()), time);
+        }
+        Ok(Self { first_seen })
+    }
+
+    /// Write the history, for the next scan to load.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::from("# Findings seen by kamino, with when each was first seen\n");
+        for ((repo, check, subject), time) in &self.first_seen {
+            let _ = writeln!(text, "{time}\t{check}\t{subject}\t{}", repo.display());
+        }
+        fs::write(path, text)
+    }
+
+    /// Set when each of the report's findings was first seen, if an earlier scan found it too, and remember them
+    /// in place of what the repo had before. Findings which are gone are forgotten, so they start over if they
+    /// come back.
+    pub fn update(&mut self, report: &mut RepoReport, now: i64) {
+        let mut previous = BTreeMap::new();
+        self.first_seen.retain(|(path, check, subject), time| {
+            if *path == report.path {
+                previous.insert((check.clone(), subject.clone()), *time);
+                false
+            } else {
+                true
+            }
+        });
+        for finding in &mut report.findings {
+            let (check, subject) = (finding.check.as_str().to_owned(), subject(finding));
+            finding.first_seen = previous.get(&(check.clone(), subject.clone())).copied();
+            self.first_seen.insert(
+                (report.path.clone(), check, subject),
+                finding.first_seen.unwrap_or(now),
+            );
+        }
+    }
+}
+
+// What a finding is about: the first reference in its message, e.g. a branch, or nothing if it has none. Tabs and
+// newlines are replaced so that it fits on a line of the history file.
+fn subject(finding: &Finding) -> String {
+    finding
+        .message
+        .0
+        .iter()
+        .find_map(|segment| match segment {
+            Segment::Ref { text, .. } => Some(text.replace(['\t', '\n'], " ")),
+            Segment::Text(_) | Segment::Time(_) => None,
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::findings::{Check, Message};
+
+    fn ahead(branch: &str, commits: usize) -> Finding {
+        Finding::new(
+            Check::Ahead,
+            Message::new()
+                .text("Branch ")
+                .reference(branch, None)
+                .text(format!(" is ahead by {commits} commits")),
+        )
+    }
+
+    #[test]
+    fn first_seen() {
+        let mut history = History::default();
+        let mut first = RepoReport::for_test(
+            "kamino",
+            vec![
+                ahead("main", 1),
+                Finding::new(Check::Uncommitted, "Has uncommitted changes"),
+            ],
+        );
+        history.update(&mut first, 1000);
+        assert!(first.findings.iter().all(|f| f.first_seen.is_none()));
+
+        // The same findings, even with different messages, keep when they were first seen
+        let mut second = RepoReport::for_test(
+            "kamino",
+            vec![
+                ahead("main", 2),
+                ahead("wip", 1),
+                Finding::new(Check::Uncommitted, "Has uncommitted changes"),
+            ],
+        );
+        history.update(&mut second, 2000);
+        let first_seen: Vec<_> = second.findings.iter().map(|f| f.first_seen).collect();
+        assert_eq!(first_seen, vec![Some(1000), None, Some(1000)]);
+
+        // Once a finding is gone it starts over
+        let mut third = RepoReport::for_test("kamino", vec![ahead("wip", 1)]);
+        history.update(&mut third, 3000);
+        history.update(&mut second, 4000);
+        let first_seen: Vec<_> = second.findings.iter().map(|f| f.first_seen).collect();
+        assert_eq!(first_seen, vec![None, Some(2000), None]);
+    }
+
+    #[test]
+    fn save_load() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(HISTORY_FILENAME);
+        assert_eq!(History::load(&path).unwrap(), History::default());
+
+        let mut history = History::default();
+        history.update(
+            &mut RepoReport::for_test("kamino", vec![ahead("odd\tname", 1)]),
+            1000,
+        );
+        history.save(&path).unwrap();
+        assert_eq!(History::load(&path).unwrap(), history);
+
+        fs::write(&path, "1000\tahead\n").unwrap();
+        assert!(History::load(&path).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::glob::{Glob, GlobError};
+use std::str::FromStr;
+
+/// Name of the file in the scanned directory which lists directories not to scan.
+pub const DEFAULT_FILENAME: &str = ".kaminoignore";
+
+/// Directories not to scan, from a `.kaminoignore` file in the scanned directory, so that exclusions for one machine
+/// can live next to its repos.
+///
+/// The syntax is gitignore's, matched against the names of the directories in the scanned directory: one pattern
+/// per line, with blank lines and lines starting with `#` skipped. A leading `!` scans a directory which an earlier
+/// pattern excluded, and the last pattern that matches wins. A leading or trailing `/` is allowed but makes no
+/// difference, since only directories directly in the scanned directory are matched. A leading `\` escapes a `#` or
+/// `!` which is part of the pattern.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IgnoreFile {
+    rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Rule {
+    glob: Glob,
+    negated: bool,
+}
+
+/// Error returned when a line of an ignore file isn't a valid pattern.
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+#[error("bad pattern on line {line}")]
+pub struct IgnoreError {
+    line: usize,
+    #[source]
+    source: GlobError,
+}
+
+impl IgnoreFile {
+    /// Check whether the directory with this name shouldn't be scanned.
+    pub fn is_ignored(&self, dir_name: &str) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.glob.matches(dir_name))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+impl FromStr for IgnoreFile {
+    type Err = IgnoreError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut rules = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+            let pattern = pattern.strip_suffix('/').unwrap_or(pattern);
+            let glob = pattern.parse().map_err(|source| IgnoreError {
+                line: i + 1,
+                source,
+            })?;
+            rules.push(Rule { glob, negated });
+        }
+        Ok(Self { rules })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ignore_file() {
+        let ignore: IgnoreFile = "# scratch clones\n\
+                                  tmp-*\n\
+                                  !tmp-keep\n\
+                                  /archive/\n\
+                                  \\#weird\n\
+                                  \n"
+        .parse()
+        .unwrap();
+        assert!(ignore.is_ignored("tmp-1"));
+        assert!(!ignore.is_ignored("tmp-keep"));
+        assert!(ignore.is_ignored("archive"));
+        assert!(ignore.is_ignored("#weird"));
+        assert!(!ignore.is_ignored("project"));
+        assert!(!IgnoreFile::default().is_ignored("project"));
+
+        assert_eq!(
+            "ok\n[abc\n".parse::<IgnoreFile>(),
+            Err(IgnoreError {
+                line: 2,
+                source: GlobError::UnclosedClass(String::from("[abc")),
+            })
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Exit code when a scan was interrupted with Ctrl-C, as a shell reports for SIGINT.
+pub const EXIT_INTERRUPTED: u8 = 130;
+
+/// How often [`sleep()`] wakes up to see whether it was interrupted.
+const POLL: Duration = Duration::from_millis(100);
+
+static FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// A flag which Ctrl-C sets, so that a scan can stop after the repo it is checking and still report the results so
+/// far. The handler is set up by the first call; from then on, a second Ctrl-C exits straight away.
+pub fn flag() -> Arc<AtomicBool> {
+    FLAG.get_or_init(|| {
+        let flag = Arc::new(AtomicBool::new(false));
+        // If the handlers can't be set up, Ctrl-C still exits as usual
+        let sigint = signal_hook::consts::SIGINT;
+        let _ = signal_hook::flag::register_conditional_shutdown(
+            sigint,
+            i32::from(EXIT_INTERRUPTED),
+            flag.clone(),
+        );
+        let _ = signal_hook::flag::register(sigint, flag.clone());
+        flag
+    })
+    .clone()
+}
+
+/// Whether Ctrl-C was pressed since [`flag()`] set up the handler.
+pub fn interrupted() -> bool {
+    FLAG.get().is_some_and(|flag| flag.load(Ordering::Relaxed))
+}
+
+/// Sleep for `duration`, or until interrupted.
+pub fn sleep(duration: Duration) {
+    let end = Instant::now() + duration;
+    while !interrupted() {
+        let left = end.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        thread::sleep(left.min(POLL));
+    }
+}
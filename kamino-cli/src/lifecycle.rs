@@ -0,0 +1,107 @@
+use kamino_core::Activity;
+use std::fmt;
+
+/// Seconds in a day.
+const DAY: i64 = 24 * 60 * 60;
+
+/// Where a repo is in its life on this machine, judged by how recently it was cloned and worked on.
+///
+/// Repos move through the stages as a state machine driven by the activity history, and `kamino diff` reports each
+/// transition since the manifest last recorded the stages:
+///
+/// ```text
+/// new --(new_days after cloning)--> active --(idle stale_days)--> stale --(idle archived_days)--> archived
+///                                     ^                                                               |
+///                                     +------------------------ any activity -------------------------+
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Stage {
+    /// Cloned recently.
+    New,
+    /// Worked on recently.
+    Active,
+    /// Not worked on for a while.
+    Stale,
+    /// Not worked on for so long that it is probably finished with.
+    Archived,
+}
+
+impl Stage {
+    pub const ALL: [Self; 4] = [Self::New, Self::Active, Self::Stale, Self::Archived];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::Active => "active",
+            Self::Stale => "stale",
+            Self::Archived => "archived",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How many days it takes to move between stages.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Thresholds {
+    /// A repo stays new for this long after it is cloned.
+    pub new_days: u32,
+    /// A repo becomes stale after this long without activity.
+    pub stale_days: u32,
+    /// A repo becomes archived after this long without activity.
+    pub archived_days: u32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            new_days: 14,
+            stale_days: 90,
+            archived_days: 365,
+        }
+    }
+}
+
+impl Thresholds {
+    /// Classify a repo by its activity, as of `now` (seconds since the Unix epoch).
+    pub fn classify(&self, activity: &Activity, now: i64) -> Stage {
+        let idle = now - activity.last;
+        if idle >= i64::from(self.archived_days) * DAY {
+            Stage::Archived
+        } else if idle >= i64::from(self.stale_days) * DAY {
+            Stage::Stale
+        } else if now - activity.first < i64::from(self.new_days) * DAY {
+            Stage::New
+        } else {
+            Stage::Active
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn classify() {
+        let thresholds = Thresholds::default();
+        let now = 1000 * DAY;
+        let stage = |first_days_ago: i64, last_days_ago: i64| {
+            let activity = Activity {
+                first: now - first_days_ago * DAY,
+                last: now - last_days_ago * DAY,
+            };
+            thresholds.classify(&activity, now)
+        };
+
+        assert_eq!(stage(1, 0), Stage::New);
+        assert_eq!(stage(14, 0), Stage::Active);
+        assert_eq!(stage(200, 89), Stage::Active);
+        assert_eq!(stage(200, 90), Stage::Stale);
+        assert_eq!(stage(400, 365), Stage::Archived);
+    }
+}
@@ -0,0 +1,53 @@
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{self, IsTerminal},
+    path::Path,
+    sync::Mutex,
+};
+use tracing_subscriber::{
+    filter::LevelFilter, fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+/// What goes into the log file: everything kamino itself logs, including how each fetch went.
+const LOG_FILE_FILTER: &str = "kamino=debug";
+
+/// Start logging, if anything is to be logged: to stderr as `RUST_LOG` says, with how long each span took, e.g.
+/// for `RUST_LOG=kamino=debug`, and to the end of `log_file` if given, whatever `RUST_LOG` says. Lines in the file
+/// have a timestamp and no colors, so that the logs of scheduled runs can be kept in one place and searched.
+///
+/// # Errors
+///
+/// Fails if the log file can't be opened.
+pub fn init(log_file: Option<&Path>) -> io::Result<()> {
+    let stderr = env::var_os("RUST_LOG").is_some().then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_span_events(FmtSpan::CLOSE)
+            .with_writer(io::stderr)
+            .with_ansi(io::stderr().is_terminal())
+            .with_filter(EnvFilter::from_default_env())
+    });
+    let file = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            let filter = EnvFilter::builder()
+                .with_default_directive(LevelFilter::OFF.into())
+                .parse_lossy(LOG_FILE_FILTER);
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(Mutex::new(file))
+                    .with_ansi(false)
+                    .with_filter(filter),
+            )
+        }
+        None => None,
+    };
+    if stderr.is_some() || file.is_some() {
+        tracing_subscriber::registry()
+            .with(stderr)
+            .with(file)
+            .init();
+    }
+    Ok(())
+}
@@ -0,0 +1,2884 @@
+#![deny(unsafe_code)]
+
+mod cache;
+mod changed;
+mod config;
+mod findings;
+mod fixture;
+mod forge;
+mod glob;
+#[cfg(test)]
+mod golden;
+mod history;
+mod ignore;
+mod interrupt;
+mod json;
+mod lifecycle;
+mod logging;
+mod manifest;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod output;
+mod prompt;
+mod query;
+mod render;
+mod report;
+#[cfg(feature = "self_update")]
+mod self_update;
+#[cfg(feature = "share")]
+mod share;
+mod watch;
+#[cfg(feature = "webhook")]
+mod webhook;
+mod zone;
+
+use anyhow::Context;
+use cache::Cache;
+use changed::{Cutoff, DAY};
+use clap::{Parser, Subcommand};
+use config::{Config, Problem};
+use findings::{Check, FailOn, Finding, Fix, Message, Outcome, RemediationResult, RepoReport};
+use forge::Forge;
+use git2::{Repository, RepositoryState};
+use history::History;
+use ignore::IgnoreFile;
+use kamino_core::{
+    BaseError, BranchFilter, Clock, CredentialPrompt, DefaultBranch, DirtyStats, Drift,
+    FetchLimits, FetchPolicy, FetchProgress, Fs, HookState, LfsProblem, MissingRemote, NestedKind,
+    PermissionProblem, RealFs, ScanOptions, ShebangProblem, SignatureProblem, Stash, SystemClock,
+};
+use manifest::{Change, Manifest};
+use output::Output;
+use prompt::TerminalPrompt;
+use query::Query;
+use render::{RenderArgs, Renderer, Timestamps};
+use report::{format_bytes, DocumentArgs, Format, Kind};
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    ffi::OsStr,
+    fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    slice,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{debug, info, info_span};
+use watch::Interval;
+use zone::TimeZone;
+
+#[derive(Parser)]
+#[clap(author, version, about, long_about = None)] // Read from `Cargo.toml`
+#[clap(args_conflicts_with_subcommands = true)]
+struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    #[clap(flatten)]
+    scan: ScanArgs,
+
+    #[clap(flatten)]
+    document: DocumentArgs,
+
+    #[clap(flatten)]
+    render: RenderArgs,
+
+    /// Also append a timestamped log of everything to this file, whatever is printed: every repo and its findings,
+    /// including clean ones, and how each fetch went, e.g. to keep a record of scheduled scans
+    #[clap(long, global = true, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+struct ScanArgs {
+    #[clap(default_value = ".")]
+    dir: PathBuf,
+
+    /// Config file to use. Defaults to `.kaminoconfig` in the scanned directory, if present.
+    #[clap(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Fetch from every remote and compare branches with all of them, not just the configured one
+    #[clap(long)]
+    all_remotes: bool,
+
+    /// Give up on a fetch after this many seconds, reporting it instead of waiting, so that an unreachable remote
+    /// can't stall the scan
+    #[clap(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    fetch_timeout: Option<u64>,
+
+    /// Fetch at most this many repos from the same host at once. A fetch given up on after --fetch-timeout may
+    /// still be connected, and is counted until it stops, so that a struggling server isn't sent more and more
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    fetches_per_host: Option<u64>,
+
+    /// Don't fetch repos which were fetched less than this long ago, e.g. 1h, going by the time of FETCH_HEAD, so
+    /// that scanning again soon after is fast
+    #[clap(long, value_name = "DURATION", value_parser = config::parse_duration)]
+    fetch_ttl: Option<Duration>,
+
+    /// Reuse the results of repos checked less than this long ago, e.g. 1h, if nothing has changed in them since:
+    /// no commits, branches, staging or fetches. Kept in .kaminocache in the scanned directory
+    #[clap(long, value_name = "DURATION", value_parser = config::parse_duration)]
+    cache_ttl: Option<Duration>,
+
+    /// Report repos which haven't been fetched for longer than this, e.g. 7d, going by the time of FETCH_HEAD or the
+    /// remote-tracking branches' reflogs. A fetch during the scan counts, so this is mostly useful with --quick,
+    /// e.g. on machines kept as backups
+    #[clap(long, value_name = "DURATION", value_parser = config::parse_duration)]
+    max_fetch_age: Option<Duration>,
+
+    /// Prune remote-tracking branches which were deleted on the remote while fetching, like `git fetch --prune`
+    #[clap(long)]
+    prune: bool,
+
+    /// Fetch only this refspec instead of each remote's configured ones, e.g. a branch name such as main. May be
+    /// given multiple times
+    #[clap(long, value_name = "REFSPEC")]
+    refspec: Vec<String>,
+
+    /// Only compare these local branches with their upstreams: HEAD for the checked-out branch, or a comma
+    /// separated list of names or patterns, e.g. main,develop or release/*
+    #[clap(long, value_name = "HEAD|BRANCHES", value_parser = config::parse_branches)]
+    branches: Option<BranchFilter>,
+
+    /// Also compare every local branch with this base, e.g. origin/main or origin/HEAD for the remote's default
+    /// branch, and report branches that are behind it
+    #[clap(long, value_name = "REF")]
+    base: Option<String>,
+
+    /// Report repos where nothing has been committed on any local branch for this long, e.g. 180d, to find clones
+    /// which could be archived
+    #[clap(long, value_name = "DURATION", value_parser = config::parse_duration)]
+    stale_after: Option<Duration>,
+
+    /// With --stale-after, only report repos which also haven't been checked out, pulled and so on for that long,
+    /// going by the reflog of HEAD
+    #[clap(long)]
+    stale_activity: bool,
+
+    /// Ask on the terminal for a username and password, or an SSH key's passphrase, when a fetch needs them and
+    /// nothing else has them
+    #[clap(long)]
+    ask_credentials: bool,
+
+    /// Store the credentials asked for with git's credential helper once they have worked
+    #[clap(long, requires = "ask-credentials")]
+    store_credentials: bool,
+
+    /// Exit with code 1 if any finding matches. Either a level (info, warning, error), which also
+    /// matches all higher levels, or a check name (uncommitted, stashed, state, remote,
+    /// remote-url, ahead, behind, tags, merged, submodules, worktrees, hooks). May be given multiple times.
+    #[clap(long, value_name = "LEVEL|CHECK")]
+    fail_on: Vec<FailOn>,
+
+    /// Print only repos with findings, without the banner, completion message or summary
+    #[clap(long, short)]
+    quiet: bool,
+
+    /// Also list repos with no findings, to confirm that every repo was scanned
+    #[clap(long, short, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Print nothing at all if no repo has findings, e.g. so cron only sends mail when there is something to see.
+    #[clap(long)]
+    quiet_when_clean: bool,
+
+    /// Fix problems automatically where it is safe to: hooks (copy hooks from .githooks into
+    /// .git/hooks), pull (fast-forward branches that are behind and have no commits of their own),
+    /// push (push branches that are ahead and not behind), prune (delete remote-tracking branches
+    /// gone from the remote), branches (delete merged branches whose upstream was deleted),
+    /// unshallow (fetch the full history of shallow clones, so that ahead/behind counts are exact;
+    /// needs git installed). May be given multiple times.
+    #[clap(long, value_name = "WHAT")]
+    fix: Vec<Fix>,
+
+    /// Only print what --fix would do, without changing anything
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Only check for uncommitted changes and whether the checked-out branch is ahead of or behind its
+    /// upstream, without fetching. Much faster, but finds less.
+    #[clap(long, conflicts_with_all = &["fix", "dry-run", "all-remotes"])]
+    quick: bool,
+
+    /// In-repo hook directory to compare with .git/hooks instead of .githooks, e.g. .husky or scripts/git-hooks.
+    /// May be given multiple times, in order of precedence.
+    #[clap(long, value_name = "DIR")]
+    hooks_dir: Vec<String>,
+
+    /// Print a unified diff under each hook which is different in .githooks and .git/hooks
+    #[clap(long)]
+    diff: bool,
+
+    /// Skip repos with no activity (judged by modification times in the git directory and the top of the working
+    /// directory) since DATE, given as YYYY-MM-DD
+    #[clap(long, value_name = "DATE")]
+    changed_since: Option<Cutoff>,
+
+    /// Skip repos with no activity since the last scan run with this option, which is recorded in the scanned
+    /// directory
+    #[clap(long, conflicts_with = "changed-since")]
+    since_last_scan: bool,
+
+    /// Save the results of the scan to FILE, to replay later with --replay
+    #[clap(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+
+    /// Fail if a directory can't be read, instead of skipping it. Unreadable directories are listed with --verbose.
+    #[clap(long)]
+    strict_discovery: bool,
+
+    /// Don't scan, but report the results recorded with --record in FILE instead
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = &["record", "fix", "dry-run", "changed-since", "since-last-scan"]
+    )]
+    replay: Option<PathBuf>,
+
+    /// Publish results to this MQTT broker (`host`, `host:port` or `[ipv6]:port`)
+    #[cfg(feature = "mqtt")]
+    #[clap(long, value_name = "HOST[:PORT]")]
+    mqtt_broker: Option<String>,
+
+    /// Topic prefix for MQTT messages [default: kamino]
+    #[cfg(feature = "mqtt")]
+    #[clap(long, value_name = "TOPIC")]
+    mqtt_topic: Option<String>,
+
+    /// POST a JSON summary of the findings to this URL when there are any, e.g. a Slack incoming webhook
+    #[cfg(feature = "webhook")]
+    #[clap(long, value_name = "URL")]
+    notify_webhook: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect the configuration
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+
+    /// Compare the repos in the directory with the manifest of repos seen before, and report which were
+    /// added, removed or moved, and which moved to another lifecycle stage
+    Diff {
+        /// Record the current repos in the manifest, including new paths of moved repos and new stages
+        #[clap(long)]
+        update: bool,
+
+        /// Manifest file to use [default: .kaminomanifest in the scanned directory]
+        #[clap(long, value_name = "FILE")]
+        manifest: Option<PathBuf>,
+
+        #[clap(flatten)]
+        scan: ScanArgs,
+    },
+
+    /// Search the tracked files of every repo for lines containing a fixed string, e.g. to find which repos
+    /// still reference an old API host
+    Grep {
+        /// Text to search for
+        pattern: String,
+
+        /// Search the files as committed on the checked-out branch, instead of the working directory
+        #[clap(long)]
+        cached: bool,
+
+        /// Only search repos which have uncommitted changes (dirty), or don't (clean)
+        #[clap(long, value_enum, value_name = "WHICH")]
+        filter: Option<RepoFilter>,
+
+        #[clap(flatten)]
+        scan: ScanArgs,
+    },
+
+    /// Scan, then print the path of each repo matching a query on what was found in it, one per line, e.g. for
+    /// scripts
+    List {
+        /// Only list repos matching QUERY, e.g. "behind > 10 && !dirty" or "has(hooks)". Fields are ahead,
+        /// behind, stashes, hook_problems, at_risk, findings, dirty, clean, name, path and stage; has() takes a
+        /// check or level.
+        #[clap(long = "where", value_name = "QUERY")]
+        query: Option<Query>,
+
+        #[clap(flatten)]
+        scan: ScanArgs,
+    },
+
+    /// Scan with a fix turned on, e.g. `kamino fix pull` to bring every clone up to date
+    Fix {
+        /// What to fix (same as --fix)
+        #[clap(value_name = "WHAT")]
+        what: Fix,
+
+        #[clap(flatten)]
+        scan: ScanArgs,
+    },
+
+    /// Bring every clone up to date where it is provably safe: prune deleted remote branches, fast-forward
+    /// branches that are behind, delete merged branches whose upstream was deleted, and sync hooks. Shows the
+    /// plan and asks before changing anything.
+    Sync {
+        /// Don't ask for confirmation
+        #[clap(long, short)]
+        yes: bool,
+
+        #[clap(flatten)]
+        scan: ScanArgs,
+    },
+
+    /// Keep running and scan again every so often, only printing repos whose findings changed since the
+    /// previous scan
+    Watch {
+        /// Time between scans, e.g. 30m, 1h30m or 45s
+        #[clap(long, value_name = "TIME", default_value = "5m")]
+        interval: Interval,
+
+        #[clap(flatten)]
+        scan: ScanArgs,
+    },
+
+    /// Do something with the scan report
+    #[cfg(feature = "share")]
+    #[clap(subcommand)]
+    Report(ReportCommand),
+
+    /// Check for a newer release of kamino and install it
+    #[cfg(feature = "self_update")]
+    SelfUpdate {
+        /// Only check for a newer release and print how to upgrade
+        #[clap(long)]
+        check: bool,
+    },
+}
+
+/// Which repos `kamino grep` searches.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum RepoFilter {
+    /// Repos with uncommitted changes.
+    Dirty,
+    /// Repos without uncommitted changes.
+    Clean,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Check the config file for unknown keys, bad values and conflicting settings
+    Validate(ScanArgs),
+    /// Print the settings in the config file
+    Show {
+        /// Print the fully merged configuration: defaults, then the config file, then command line options
+        #[clap(long)]
+        effective: bool,
+
+        #[clap(flatten)]
+        scan: ScanArgs,
+    },
+}
+
+#[cfg(feature = "share")]
+#[derive(Subcommand)]
+enum ReportCommand {
+    /// Scan, then upload the report and print a URL where it can be viewed
+    Share {
+        /// Where to upload the report: "gist" (needs GITHUB_TOKEN) or a URL to PUT it to. Defaults to `share.to`
+        /// in the config file.
+        #[clap(long, value_name = "DEST")]
+        to: Option<share::Destination>,
+
+        /// Format of the uploaded report
+        #[clap(long, value_enum, default_value = "markdown")]
+        format: report::Format,
+
+        #[clap(flatten)]
+        scan: ScanArgs,
+    },
+}
+
+/// Exit code when a finding matches `--fail-on`, or the config is invalid.
+const EXIT_FINDINGS: u8 = 1;
+/// Exit code when a repo couldn't be checked.
+const EXIT_ERROR: u8 = 2;
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut output = Output::new(Renderer::new(&args.render));
+    if let Err(e) = logging::init(args.log_file.as_deref()) {
+        let e = anyhow::Error::new(e).context(format!(
+            "Failed to open log file {}",
+            args.log_file.unwrap_or_default().display()
+        ));
+        print_error(&e, &output);
+        return ExitCode::from(EXIT_ERROR);
+    }
+
+    let result = match args.command {
+        None => scan(&args.scan, &args.document, &mut output),
+        Some(Command::Fix {
+            what,
+            scan: mut scan_args,
+        }) => {
+            scan_args.fix.push(what);
+            let document = DocumentArgs {
+                format: None,
+                output: Vec::new(),
+            };
+            scan(&scan_args, &document, &mut output)
+        }
+        Some(Command::Sync { yes, scan }) => sync(&scan, yes, &mut output),
+        Some(Command::Watch { interval, scan }) => watch(&scan, interval, &mut output),
+        Some(Command::Diff {
+            update,
+            manifest,
+            scan,
+        }) => diff(&scan, manifest, update, &mut output),
+        Some(Command::Grep {
+            pattern,
+            cached,
+            filter,
+            scan,
+        }) => grep(&scan, &pattern, cached, filter, &mut output),
+        Some(Command::List { query, scan }) => list(&scan, query.as_ref(), &mut output),
+        Some(Command::Config(ConfigCommand::Validate(scan))) => validate_config(&scan, &mut output),
+        Some(Command::Config(ConfigCommand::Show { effective, scan })) => {
+            show_config(&scan, effective, &mut output)
+        }
+        #[cfg(feature = "share")]
+        Some(Command::Report(ReportCommand::Share { to, format, scan })) => {
+            share_report(&scan, to, format, &mut output)
+        }
+        #[cfg(feature = "self_update")]
+        Some(Command::SelfUpdate { check }) => self_update::run(check, &mut output),
+    };
+
+    prompt::restore_echo();
+    result.unwrap_or_else(|e| {
+        print_error(&e, &output);
+        ExitCode::from(EXIT_ERROR)
+    })
+}
+
+fn print_error(e: &anyhow::Error, output: &Output) {
+    output.error(format!("Error: {}", e));
+    let mut source = e.source();
+    while let Some(cause) = source {
+        output.error(format!("Caused by: {}", cause));
+        source = cause.source();
+    }
+}
+
+// Find the config file: either given explicitly or the default one in the scanned dir, if it exists.
+fn config_path(args: &ScanArgs) -> Option<PathBuf> {
+    args.config.clone().or_else(|| {
+        let path = args.dir.join(config::DEFAULT_FILENAME);
+        path.is_file().then_some(path)
+    })
+}
+
+// Load the config file and apply command line options on top of it.
+fn load_config(args: &ScanArgs) -> anyhow::Result<(Config, Vec<Problem>)> {
+    let (mut config, problems) = match config_path(args) {
+        Some(path) => {
+            if !path.is_file() {
+                anyhow::bail!("config file {} not found", path.display());
+            }
+            Config::load(&path)?
+        }
+        None => (Config::default(), Vec::new()),
+    };
+
+    config.fail_on.extend(args.fail_on.iter().copied());
+    config.all_remotes |= args.all_remotes;
+    config.prune |= args.prune;
+    if let Some(branches) = &args.branches {
+        config.branches = branches.clone();
+    }
+    if let Some(base) = &args.base {
+        config.base = Some(base.clone());
+    }
+    if let Some(after) = args.stale_after {
+        config.stale_after = Some(after);
+    }
+    config.stale_activity |= args.stale_activity;
+    config.ask_credentials |= args.ask_credentials;
+    config.store_credentials |= args.store_credentials;
+    if let Some(seconds) = args.fetch_timeout {
+        config.fetch_timeout = Some(Duration::from_secs(seconds));
+    }
+    if let Some(limit) = args.fetches_per_host {
+        config.fetches_per_host = Some(limit);
+    }
+    if let Some(ttl) = args.fetch_ttl {
+        config.fetch_ttl = Some(ttl);
+    }
+    if let Some(ttl) = args.cache_ttl {
+        config.cache_ttl = Some(ttl);
+    }
+    if let Some(age) = args.max_fetch_age {
+        config.max_fetch_age = Some(age);
+    }
+    config.quiet |= args.quiet;
+    config.quiet_when_clean |= args.quiet_when_clean;
+    config.strict_discovery |= args.strict_discovery;
+    config.fix.extend(args.fix.iter().copied());
+    config.dry_run = args.dry_run;
+    config.quick = args.quick;
+    config.verbose = args.verbose;
+    config.diff = args.diff;
+    // Given on the command line, the hook directories are used for every repo
+    if !args.refspec.is_empty() {
+        config.refspecs = args.refspec.clone();
+        for repo in config.repos.values_mut() {
+            repo.refspecs.clear();
+        }
+    }
+    if !args.hooks_dir.is_empty() {
+        config.hook_sources = args.hooks_dir.clone();
+        for repo in config.repos.values_mut() {
+            repo.hook_sources.clear();
+        }
+    }
+    config.changed_since = args.changed_since;
+    #[cfg(feature = "mqtt")]
+    {
+        if let Some(broker) = &args.mqtt_broker {
+            config.mqtt_broker = Some(broker.clone());
+        }
+        if let Some(topic) = &args.mqtt_topic {
+            config.mqtt_topic = topic.clone();
+        }
+    }
+    #[cfg(feature = "webhook")]
+    if let Some(url) = &args.notify_webhook {
+        config.notify_webhook = Some(url.clone());
+    }
+
+    Ok((config, problems))
+}
+
+fn validate_config(args: &ScanArgs, output: &mut Output) -> anyhow::Result<ExitCode> {
+    let (_, problems) = load_config(args)?;
+    match config_path(args) {
+        Some(path) => output.report(format!("Validating {}", path.display())),
+        None => output.report("No config file found, using defaults"),
+    }
+
+    for problem in &problems {
+        output.report(format!("    {problem}"));
+    }
+
+    if problems.is_empty() {
+        output.report("Config is valid");
+        Ok(ExitCode::SUCCESS)
+    } else {
+        output.report(format!("Found {} problems", problems.len()));
+        Ok(ExitCode::from(EXIT_FINDINGS))
+    }
+}
+
+fn show_config(args: &ScanArgs, effective: bool, output: &mut Output) -> anyhow::Result<ExitCode> {
+    if effective {
+        let (config, problems) = load_config(args)?;
+        for problem in &problems {
+            output.error(format!("Warning: {problem}"));
+        }
+        for line in config.to_string().lines() {
+            output.report(line);
+        }
+    } else if let Some(path) = config_path(args) {
+        for (name, value) in Config::read_entries(&path)? {
+            output.report(format!("{name}={value}"));
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn scan(args: &ScanArgs, document: &DocumentArgs, output: &mut Output) -> anyhow::Result<ExitCode> {
+    let (mut config, problems) = load_config(args)?;
+    for problem in &problems {
+        output.error(format!("Warning: {problem}"));
+    }
+
+    // A document printed to stdout replaces the usual text, except for GitHub's workflow commands, which the job's
+    // log hides
+    let outputs = document.outputs().map_err(anyhow::Error::msg)?;
+    let as_text = outputs.iter().all(|(kind, path)| {
+        path.is_some() || matches!(kind, Kind::Text | Kind::Document(Format::Github))
+    });
+    config.quiet |= !as_text;
+    output.set_quiet_when_clean(config.quiet_when_clean);
+
+    // NDJSON or workflow commands going to stdout are written a repo at a time, so that they can be piped somewhere
+    // or show up while the scan runs
+    let stream = outputs.iter().find_map(|(kind, path)| match (kind, path) {
+        (Kind::Document(format), None) if format.streamed() => Some(*format),
+        _ => None,
+    });
+    let reports = check_repos(args, &config, output, |report, output| {
+        if as_text {
+            print_report(report, config.verbose, output);
+        }
+        if let Some(format) = stream {
+            let text = report::render(
+                format,
+                "",
+                slice::from_ref(report),
+                output.renderer().timestamps(),
+            );
+            let mut stdout = io::stdout().lock();
+            let _ = stdout.write_all(text.as_bytes());
+            let _ = stdout.flush();
+        }
+    })?;
+    print_summary(&reports, output);
+
+    // Only worked out when needed, since a replayed scan's directory may not exist here
+    let title = || -> anyhow::Result<String> {
+        Ok(format!(
+            "Kamino report for {}",
+            args.dir.canonicalize()?.display()
+        ))
+    };
+    // Every output is rendered from the same results, so the repos are only scanned once
+    for (kind, path) in &outputs {
+        let text = match (kind, path) {
+            (Kind::Text, None) => continue,
+            (Kind::Document(format), None) if format.streamed() => continue,
+            (Kind::Text, Some(_)) => {
+                let mut text = Output::capture(output.renderer().plain());
+                for report in &reports {
+                    print_report(report, config.verbose, &mut text);
+                }
+                print_summary(&reports, &mut text);
+                text.into_captured()
+            }
+            (Kind::Document(format), _) => {
+                report::render(*format, &title()?, &reports, output.renderer().timestamps())
+            }
+        };
+        match path {
+            Some(path) => {
+                report::write(path, &text)
+                    .with_context(|| format!("Failed to write report to {}", path.display()))?;
+                output.status(format!("Report written to {}", path.display()));
+            }
+            None => print!("{text}"),
+        }
+    }
+
+    #[cfg(feature = "mqtt")]
+    if let Some(broker) = &config.mqtt_broker {
+        let timestamps = output.renderer().timestamps();
+        mqtt::publish(broker, &config.mqtt_topic, &reports, timestamps)?;
+    }
+
+    #[cfg(feature = "webhook")]
+    if let Some(url) = &config.notify_webhook {
+        if webhook::notify(url, &title()?, &reports, output.renderer().timestamps())? {
+            output.status(format!("Notified {url}"));
+        }
+    }
+
+    let failed = reports
+        .iter()
+        .flat_map(|report| &report.findings)
+        .any(|finding| config.fail_on.iter().any(|f| f.matches(finding)));
+
+    if interrupt::interrupted() {
+        Ok(ExitCode::from(interrupt::EXIT_INTERRUPTED))
+    } else if reports.iter().any(RepoReport::is_broken) {
+        Ok(ExitCode::from(EXIT_ERROR))
+    } else if failed {
+        Ok(ExitCode::from(EXIT_FINDINGS))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+// Print a repo's findings and fixes, if it has any. With `verbose`, repos without any are listed as clean.
+fn print_report(report: &RepoReport, verbose: bool, output: &mut Output) {
+    if report.findings.is_empty() && report.fixes.is_empty() {
+        if verbose {
+            let renderer = output.renderer();
+            let line = format!("{}: {}", renderer.repo_path(&report.path), renderer.clean());
+            output.status(line);
+        }
+    } else {
+        let at_risk = match report.summary.at_risk {
+            0 => String::new(),
+            bytes => format!(" ({} only in this clone)", format_bytes(bytes)),
+        };
+        let header = format!("{}:{at_risk}", output.renderer().repo_path(&report.path));
+        output.report(header);
+        for finding in &report.findings {
+            let line = format!("    {}", output.renderer().finding(finding));
+            output.report(line);
+            for detail in &finding.detail {
+                let line = format!("        {}", output.renderer().detail(detail));
+                output.report(line);
+            }
+        }
+        for fix in &report.fixes {
+            let prefix = match fix.outcome {
+                Outcome::Failed(_) => "Fix failed",
+                Outcome::Applied | Outcome::Planned => "Fixed",
+            };
+            let line = format!(
+                "    {prefix}: {}",
+                output.renderer().message(&fix.describe())
+            );
+            output.report(line);
+        }
+    }
+    if verbose {
+        for fetch in &report.fetches {
+            output.status(format!("    Fetched {}", describe_fetch(fetch)));
+        }
+    }
+}
+
+// Describe what was fetched from a remote, e.g. "origin: 340 objects, 1.2 MB in 2.3s".
+fn describe_fetch(fetch: &kamino_core::FetchStats) -> String {
+    let seconds = fetch.duration.as_secs_f64();
+    match fetch.received_objects {
+        0 => format!("{}: up to date in {seconds:.1}s", fetch.remote),
+        objects => format!(
+            "{}: {objects} objects, {} in {seconds:.1}s",
+            fetch.remote,
+            format_bytes(fetch.received_bytes as u64)
+        ),
+    }
+}
+
+// A progress bar for a fetch which is under way, with how much has been received.
+fn describe_progress(dir: &Path, progress: &FetchProgress) -> String {
+    const WIDTH: usize = 20;
+    let filled = (progress.received_objects * WIDTH)
+        .checked_div(progress.total_objects)
+        .unwrap_or(0)
+        .min(WIDTH);
+    format!(
+        "Fetching {} for {}: [{}{}] {}/{} objects, {}",
+        progress.remote,
+        dir.display(),
+        "#".repeat(filled),
+        " ".repeat(WIDTH - filled),
+        progress.received_objects,
+        progress.total_objects,
+        format_bytes(progress.received_bytes as u64)
+    )
+}
+
+// Print the totals over all repos scanned.
+fn print_summary(reports: &[RepoReport], output: &mut Output) {
+    let summary: kamino_core::Summary = reports.iter().map(|report| report.summary).sum();
+    output.status(format!(
+        "Summary: {} repos scanned, {} clean",
+        summary.repos, summary.clean
+    ));
+    output.status(format!(
+        "    {} with uncommitted changes, {} stashes",
+        summary.uncommitted, summary.stashes
+    ));
+    output.status(format!(
+        "    {} branches ahead, {} branches behind",
+        summary.ahead, summary.behind
+    ));
+    output.status(format!("    {} hook problems", summary.hook_problems));
+    if summary.at_risk > 0 {
+        output.status(format!(
+            "    {} of work only exists on this machine",
+            format_bytes(summary.at_risk)
+        ));
+    }
+    let broken: Vec<_> = reports
+        .iter()
+        .filter(|report| report.is_broken())
+        .map(|report| report.path.display().to_string())
+        .collect();
+    if !broken.is_empty() {
+        output.status(format!(
+            "    {} repos couldn't be checked: {}",
+            broken.len(),
+            broken.join(", ")
+        ));
+    }
+}
+
+fn watch(args: &ScanArgs, interval: Interval, output: &mut Output) -> anyhow::Result<ExitCode> {
+    let (config, problems) = load_config(args)?;
+    for problem in &problems {
+        output.error(format!("Warning: {problem}"));
+    }
+    if args.replay.is_some() || args.record.is_some() {
+        anyhow::bail!("watch scans repeatedly, so can't be used with --replay or --record");
+    }
+
+    output.status(format!(
+        "Kamino watching repos in {:?}, scanning every {interval}",
+        args.dir
+            .canonicalize()
+            .with_context(|| format!("Failed to canonicalize {:?}", args.dir))?,
+    ));
+    // Each scan's banners would be noise, so print one line per scan instead
+    let scan_config = Config {
+        quiet: true,
+        ..config.clone()
+    };
+    let mut previous: Option<Vec<RepoReport>> = None;
+    loop {
+        match check_repos(args, &scan_config, output, |_, _| ()) {
+            // Only part of the repos were scanned, so comparing with the previous scan would be misleading
+            Ok(_) if interrupt::interrupted() => {
+                return Ok(ExitCode::from(interrupt::EXIT_INTERRUPTED))
+            }
+            Ok(reports) => {
+                output.set_quiet(config.quiet);
+                let different = match &previous {
+                    Some(previous) => watch::changed(previous, &reports),
+                    None => reports.iter().collect(),
+                };
+                for report in &different {
+                    // A repo whose findings went away is worth saying so, even without --verbose
+                    print_report(report, previous.is_some() || config.verbose, output);
+                }
+                let changes = match previous {
+                    Some(_) => format!(", {} changed", different.len()),
+                    None => String::new(),
+                };
+                output.status(format!(
+                    "Scanned {} repos at {}{changes}",
+                    reports.len(),
+                    changed::format_time(SystemClock.now())
+                ));
+                previous = Some(reports);
+            }
+            Err(e) => {
+                // Problems like a network outage may well be gone by the next scan
+                output.set_quiet(config.quiet);
+                print_error(&e, output);
+            }
+        }
+        interrupt::sleep(interval.0);
+        if interrupt::interrupted() {
+            return Ok(ExitCode::from(interrupt::EXIT_INTERRUPTED));
+        }
+    }
+}
+
+fn sync(args: &ScanArgs, yes: bool, output: &mut Output) -> anyhow::Result<ExitCode> {
+    let (mut config, problems) = load_config(args)?;
+    for problem in &problems {
+        output.error(format!("Warning: {problem}"));
+    }
+    if args.replay.is_some() || args.quick {
+        anyhow::bail!("sync needs a full scan, so can't be used with --replay or --quick");
+    }
+    config.fix = vec![Fix::Prune, Fix::Pull, Fix::Branches, Fix::Hooks];
+
+    // First work out everything that would be done, without changing anything
+    config.dry_run = true;
+    let mut planned = 0;
+    check_repos(args, &config, output, |report, output| {
+        if !report.fixes.is_empty() {
+            let header = format!("{}:", output.renderer().repo_path(&report.path));
+            output.report(header);
+            for fix in &report.fixes {
+                let prefix = match fix.outcome {
+                    Outcome::Failed(_) => "Can't plan: ",
+                    Outcome::Applied | Outcome::Planned => "",
+                };
+                let line = format!("    {prefix}{}", output.renderer().message(&fix.describe()));
+                output.report(line);
+            }
+            planned += report
+                .fixes
+                .iter()
+                .filter(|fix| fix.outcome == Outcome::Planned)
+                .count();
+        }
+    })?;
+    if planned == 0 {
+        output.report("Nothing to sync");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if !yes && !confirm(&format!("Apply {planned} changes? [y/N] "))? {
+        output.report("Nothing changed");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Then do it for real. Each fix is checked again, so anything that changed in the meantime and is no longer
+    // safe is left alone.
+    config.dry_run = false;
+    let mut failed = 0;
+    check_repos(args, &config, output, |report, output| {
+        print_report(report, config.verbose, output);
+        failed += report
+            .fixes
+            .iter()
+            .filter(|fix| fix.outcome.error().is_some())
+            .count();
+    })?;
+    if failed > 0 {
+        output.error(format!("{failed} changes failed"));
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+// Ask the user a yes / no question on the terminal.
+fn confirm(prompt: &str) -> anyhow::Result<bool> {
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        anyhow::bail!("stdin is not a terminal, pass --yes to apply changes without confirmation");
+    }
+    eprint!("{prompt}");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    stdin.read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn list(args: &ScanArgs, query: Option<&Query>, output: &mut Output) -> anyhow::Result<ExitCode> {
+    let (mut config, problems) = load_config(args)?;
+    for problem in &problems {
+        output.error(format!("Warning: {problem}"));
+    }
+    // Only the paths are printed, so they can be piped into other commands
+    config.quiet = true;
+
+    let mut listed = 0;
+    check_repos(args, &config, output, |report, output| {
+        if query.is_none_or(|query| query.matches(report)) {
+            output.report(report.path.display().to_string());
+            listed += 1;
+        }
+    })?;
+
+    // Like grep, listing nothing is a failure
+    if listed == 0 {
+        Ok(ExitCode::from(EXIT_FINDINGS))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn grep(
+    args: &ScanArgs,
+    pattern: &str,
+    cached: bool,
+    filter: Option<RepoFilter>,
+    output: &mut Output,
+) -> anyhow::Result<ExitCode> {
+    let (config, problems) = load_config(args)?;
+    for problem in &problems {
+        output.error(format!("Warning: {problem}"));
+    }
+    output.set_quiet(config.quiet);
+
+    let repos = discover(args, &config, &RealFs)?.repos;
+    let results = in_parallel(repos, |(dir, _, repo)| {
+        let result = (|| -> anyhow::Result<Vec<kamino_core::GrepMatch>> {
+            if let Some(filter) = filter {
+                let dirty = kamino_core::check_uncommitted(&repo)?;
+                if dirty != (filter == RepoFilter::Dirty) {
+                    return Ok(Vec::new());
+                }
+            }
+            Ok(kamino_core::grep(&repo, pattern, cached)?)
+        })();
+        (dir, result)
+    });
+
+    let (mut total, mut repos, mut failed) = (0, 0, false);
+    for (dir, result) in results {
+        match result {
+            Ok(matches) if matches.is_empty() => {}
+            Ok(matches) => {
+                let header = format!("{}:", output.renderer().repo_path(&dir));
+                output.report(header);
+                for found in &matches {
+                    output.report(format!(
+                        "    {}:{}: {}",
+                        found.path.display(),
+                        found.line,
+                        found.text
+                    ));
+                }
+                total += matches.len();
+                repos += 1;
+            }
+            Err(e) => {
+                output.error(format!("Error: {}: {e:#}", dir.display()));
+                failed = true;
+            }
+        }
+    }
+    output.status(format!("Found {total} matches in {repos} repos"));
+
+    // Like grep, finding nothing is a failure
+    if failed {
+        Ok(ExitCode::from(EXIT_ERROR))
+    } else if total == 0 {
+        Ok(ExitCode::from(EXIT_FINDINGS))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn diff(
+    args: &ScanArgs,
+    manifest_path: Option<PathBuf>,
+    update: bool,
+    output: &mut Output,
+) -> anyhow::Result<ExitCode> {
+    let (config, problems) = load_config(args)?;
+    for problem in &problems {
+        output.error(format!("Warning: {problem}"));
+    }
+
+    let path = manifest_path.unwrap_or_else(|| args.dir.join(manifest::DEFAULT_FILENAME));
+    let now = SystemClock.now();
+    let current = Manifest::new(
+        discover(args, &config, &RealFs)?
+            .repos
+            .into_iter()
+            .map(|(_, name, repo)| {
+                let url = repo
+                    .find_remote(config.remote_for(&name))
+                    .ok()
+                    .and_then(|remote| remote.url().map(ToOwned::to_owned));
+                let activity = kamino_core::check_activity(&repo).ok().flatten();
+                let stage = activity.map(|activity| config.lifecycle.classify(&activity, now));
+                manifest::Entry { name, url, stage }
+            })
+            .collect(),
+    );
+
+    if !path.exists() {
+        current
+            .save(&path)
+            .with_context(|| format!("failed to write manifest {}", path.display()))?;
+        output.report(format!(
+            "Saved {} repos to new manifest {}",
+            current.entries().len(),
+            path.display()
+        ));
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let recorded = Manifest::load(&path)
+        .with_context(|| format!("failed to read manifest {}", path.display()))?;
+    let changes = recorded.diff(&current);
+    for change in &changes {
+        let line = match change {
+            Change::Added(entry) => format!("Added: {}", entry.name),
+            Change::Removed(entry) => format!("Removed: {}", entry.name),
+            Change::Moved { from, to, url } => format!("Moved: {from} -> {to} ({url})"),
+            Change::Staged { name, from, to } => format!("Stage: {name} {from} -> {to}"),
+        };
+        output.report(line);
+    }
+
+    if changes.is_empty() {
+        output.report("No repos added, removed, moved or changed stage");
+        Ok(ExitCode::SUCCESS)
+    } else if update {
+        current
+            .save(&path)
+            .with_context(|| format!("failed to write manifest {}", path.display()))?;
+        output.report(format!("Updated manifest {}", path.display()));
+        Ok(ExitCode::SUCCESS)
+    } else {
+        output.report("Run with --update to record these changes in the manifest");
+        Ok(ExitCode::from(EXIT_FINDINGS))
+    }
+}
+
+#[cfg(feature = "share")]
+fn share_report(
+    args: &ScanArgs,
+    to: Option<share::Destination>,
+    format: report::Format,
+    output: &mut Output,
+) -> anyhow::Result<ExitCode> {
+    let (config, problems) = load_config(args)?;
+    for problem in &problems {
+        output.error(format!("Warning: {problem}"));
+    }
+
+    let destination = match to {
+        Some(to) => to,
+        None => config
+            .share_to
+            .as_deref()
+            .context("no destination to share to: use --to or set share.to in the config file")?
+            .parse()
+            .map_err(anyhow::Error::msg)?,
+    };
+
+    let reports = check_repos(args, &config, output, |_, _| ())?;
+    let title = format!("Kamino report for {}", args.dir.canonicalize()?.display());
+    let document = report::render(format, &title, &reports, output.renderer().timestamps());
+    let url = share::upload(&destination, format, &document)?;
+    output.report(format!("Report shared at {url}"));
+    Ok(ExitCode::SUCCESS)
+}
+
+// Check every repo in the scanned directory, calling `on_report` as each one is done.
+fn check_repos(
+    args: &ScanArgs,
+    config: &Config,
+    output: &mut Output,
+    mut on_report: impl FnMut(&RepoReport, &mut Output),
+) -> anyhow::Result<Vec<RepoReport>> {
+    output.set_quiet(config.quiet);
+    if let Some(path) = &args.replay {
+        output.status(format!("Kamino replaying scan from {}", path.display()));
+        let reports =
+            fixture::load(path).with_context(|| format!("Failed to replay {}", path.display()))?;
+        for report in &reports {
+            on_report(report, output);
+        }
+        output.status("Kamino scans complete!");
+        return Ok(reports);
+    }
+
+    output.status(format!(
+        "Kamino scanning repos in {:?}",
+        args.dir
+            .canonicalize()
+            .with_context(|| format!("Failed to canonicalize {:?}", args.dir))?,
+    ));
+
+    let last_scan_path = args.dir.join(changed::LAST_SCAN_FILENAME);
+    let cutoff = if args.since_last_scan {
+        changed::load_last_scan(&last_scan_path)
+            .with_context(|| format!("Failed to read {}", last_scan_path.display()))?
+    } else {
+        config.changed_since
+    };
+
+    let discovered = discover(args, config, &RealFs)?;
+    if config.verbose && !discovered.unreadable.is_empty() {
+        output.status(format!(
+            "Couldn't read {} directories, which might be repos: {}",
+            discovered.unreadable.len(),
+            examples(&discovered.unreadable)
+        ));
+    }
+
+    let history_path = args.dir.join(history::HISTORY_FILENAME);
+    let mut history = History::load(&history_path)
+        .with_context(|| format!("Failed to read {}", history_path.display()))?;
+    let now = SystemClock.now();
+
+    // A quick scan doesn't run every check, so its results can't stand in for a full one's
+    let cache_path = args.dir.join(cache::CACHE_FILENAME);
+    let cache_ttl = config.cache_ttl.filter(|_| !config.quick);
+    // A cache which can't be read only costs checking every repo again, and is replaced when the scan saves its own
+    let mut cache = match cache_ttl.map(|_| Cache::load(&cache_path)) {
+        Some(Ok(cache)) => cache,
+        Some(Err(e)) => {
+            let e = anyhow::Error::new(e);
+            output.error(format!("Warning: ignoring {}: {e:#}", cache_path.display()));
+            Cache::default()
+        }
+        None => Cache::default(),
+    };
+    // Anything else which changes the results means checking again, including a new version of kamino
+    let settings = format!(
+        "{config}dryRun = {}\ndiff = {}\nversion = {}\n",
+        config.dry_run,
+        config.diff,
+        env!("CARGO_PKG_VERSION")
+    );
+    let mut reused = 0;
+
+    let central_hooks = match &config.central_hooks {
+        Some(source) if !config.quick => Some(central_hooks_dir(&args.dir, source)?),
+        _ => None,
+    };
+    // Only asked for when there is someone to answer
+    let prompt: Option<Arc<dyn CredentialPrompt>> = (config.ask_credentials
+        && io::stdin().is_terminal())
+    .then(|| Arc::new(TerminalPrompt::new(config.store_credentials)) as _);
+    // Ctrl-C stops fetching and the scan ends after the repo being checked, with the results so far
+    let cancel = interrupt::flag();
+    // Shared by every repo, since a fetch given up on keeps its turn until it stops
+    let fetch_limits = config
+        .fetches_per_host
+        .map(|limit| Arc::new(FetchLimits::new(limit as usize)));
+
+    let mut reports = Vec::new();
+    let mut skipped = 0;
+    let mut unchecked = discovered.repos.len();
+    for (dir, dir_name, repo) in discovered.repos {
+        if interrupt::interrupted() {
+            break;
+        }
+        unchecked -= 1;
+        let _span = info_span!("repo", path = %dir.display()).entered();
+        let started = Instant::now();
+        if let Some(cutoff) = cutoff {
+            let modified = kamino_core::last_modified(&repo, &RealFs);
+            if let Some(modified) = modified.filter(|modified| modified.time < cutoff.0) {
+                if config.verbose {
+                    let path = match repo.workdir() {
+                        Some(workdir) if modified.path == workdir => Path::new("."),
+                        Some(workdir) => modified
+                            .path
+                            .strip_prefix(workdir)
+                            .unwrap_or(&modified.path),
+                        None => &modified.path,
+                    };
+                    output.status(format!(
+                        "Skipped {}: nothing modified since {cutoff} (latest was {} at {})",
+                        dir.display(),
+                        path.display(),
+                        output.renderer().timestamps().format(modified.time)
+                    ));
+                }
+                debug!(
+                    modified = modified.time,
+                    "skipped, since nothing was modified since the cutoff"
+                );
+                skipped += 1;
+                continue;
+            }
+        }
+
+        let cached = cache_ttl.and_then(|ttl| {
+            let key = cache::key(&repo, &settings);
+            cache.get(&dir, &key, ttl, now).cloned()
+        });
+        let mut report = if let Some(report) = cached {
+            if config.verbose {
+                output.status(format!(
+                    "Reused cached results for {}: nothing changed since it was checked",
+                    dir.display()
+                ));
+            }
+            debug!("reused cached results");
+            reused += 1;
+            report
+        } else if config.quick {
+            let (path, name) = (dir.clone(), dir_name.clone());
+            quick_check_repo(&repo, config, dir, dir_name)
+                .unwrap_or_else(|e| broken_report(path, name, &e))
+        } else {
+            let options = config
+                .scan_options(&dir_name)
+                .central_hooks(central_hooks.clone())
+                .credential_prompt(prompt.clone())
+                .cancel(Some(cancel.clone()))
+                .fetch_limits(fetch_limits.clone());
+            // Redrawn at most a few times a second, since libgit2 reports every object, and once more when done
+            let progress_output = &*output;
+            let mut last_drawn: Option<(Instant, usize)> = None;
+            let shown_dir = dir.clone();
+            let (path, name) = (dir.clone(), dir_name.clone());
+            let progress = |progress: &FetchProgress| {
+                let received = progress.received_objects;
+                let due = match last_drawn {
+                    None => true,
+                    Some((_, drawn)) if drawn == received => false,
+                    Some((at, _)) => {
+                        received == progress.total_objects
+                            || at.elapsed() >= Duration::from_millis(100)
+                    }
+                };
+                if due {
+                    progress_output.progress(describe_progress(&shown_dir, progress));
+                    last_drawn = Some((Instant::now(), received));
+                }
+            };
+            let report = check_repo(
+                repo,
+                config,
+                dir,
+                dir_name,
+                &options,
+                progress,
+                &SystemClock,
+                &RealFs,
+            );
+            output.clear_progress();
+            // One broken repo shouldn't hide the results of the rest
+            let report = match report {
+                Ok(report) => report,
+                Err(e) => {
+                    debug!(error = %format!("{e:#}"), "couldn't be checked");
+                    broken_report(path, name, &e)
+                }
+            };
+            // Keyed by the repo as the checks left it, after fetching. A fix that was applied or failed should be
+            // tried again rather than reported as done, and an interrupted fetch doesn't say anything about the remote.
+            // Nor should a repo which couldn't be checked be skipped next time.
+            let settled = !report.is_broken()
+                && report
+                    .fixes
+                    .iter()
+                    .all(|fix| matches!(fix.outcome, Outcome::Planned));
+            if cache_ttl.is_some() && settled && !options.is_cancelled() {
+                if let Ok(repo) = Repository::open(&report.path) {
+                    cache.insert(cache::key(&repo, &settings), report.clone(), now);
+                }
+            }
+            report
+        };
+        // A repo which couldn't be checked says nothing about whether its findings are gone
+        if !report.is_broken() {
+            history.update(&mut report, now);
+        }
+        for finding in &mut report.findings {
+            if let Some(days) = config.escalation_for(finding.check) {
+                finding.escalate(days, now);
+            }
+        }
+        if report.findings.is_empty() && report.fixes.is_empty() {
+            info!(duration = ?started.elapsed(), "clean");
+        } else {
+            info!(
+                findings = report.findings.len(),
+                fixes = report.fixes.len(),
+                duration = ?started.elapsed(),
+                "checked"
+            );
+        }
+        for finding in &report.findings {
+            info!(check = %finding.check, level = %finding.level, "{}", finding.message);
+        }
+        for fix in &report.fixes {
+            info!(fix = %fix.action, outcome = fix.outcome.as_str(), "{}", fix.message);
+        }
+        on_report(&report, output);
+        reports.push(report);
+    }
+
+    // A quick scan doesn't run every check, so it would forget the findings of the others
+    if !config.quick {
+        history
+            .save(&history_path)
+            .with_context(|| format!("Failed to write {}", history_path.display()))?;
+    }
+    if cache_ttl.is_some() {
+        cache
+            .save(&cache_path)
+            .with_context(|| format!("Failed to write {}", cache_path.display()))?;
+    }
+    if let Some(path) = &args.record {
+        fixture::save(path, &reports)
+            .with_context(|| format!("Failed to record scan to {}", path.display()))?;
+    }
+    if let (Some(cutoff), true) = (cutoff, skipped > 0) {
+        output.status(format!(
+            "Skipped {skipped} repos with no activity since {cutoff}"
+        ));
+    }
+    if reused > 0 {
+        output.status(format!(
+            "Reused cached results for {reused} repos with nothing changed"
+        ));
+    }
+    if interrupt::interrupted() {
+        output.status(format!(
+            "Scan interrupted, so {unchecked} repos weren't checked"
+        ));
+    }
+    // Recorded after scanning, since the scan itself fetches and so touches every repo it looks at. An interrupted
+    // scan didn't look at them all.
+    if args.since_last_scan && !interrupt::interrupted() {
+        changed::save_last_scan(&last_scan_path, SystemClock.now())
+            .with_context(|| format!("Failed to write {}", last_scan_path.display()))?;
+    }
+
+    output.status("Kamino scans complete!");
+    Ok(reports)
+}
+
+// Stand in for the report of a repo which couldn't be checked, with the error as its only finding, so that it is
+// reported under the repo like any other and the scan carries on.
+fn broken_report(dir: PathBuf, dir_name: String, e: &anyhow::Error) -> RepoReport {
+    RepoReport {
+        name: dir_name,
+        path: dir,
+        findings: vec![Finding::new(
+            Check::Broken,
+            format!("Couldn't be checked: {e:#}"),
+        )],
+        fixes: Vec::new(),
+        stage: None,
+        summary: kamino_core::Summary {
+            repos: 1,
+            ..kamino_core::Summary::default()
+        },
+        fetches: Vec::new(),
+    }
+}
+
+// Find the central hooks on disk: the configured path itself (relative to the scanned directory), or for a URL a
+// fresh clone in the temporary directory.
+fn central_hooks_dir(scanned: &Path, source: &str) -> anyhow::Result<PathBuf> {
+    if !source.contains("://") && !source.contains('@') {
+        return Ok(scanned.join(source));
+    }
+
+    let hash = format!("{:x}", Sha256::digest(source.as_bytes()));
+    let dir = env::temp_dir().join(format!("kamino-hooks-{}", &hash[..16]));
+    if dir.exists() {
+        fs::remove_dir_all(&dir)
+            .with_context(|| format!("Failed to remove old clone {}", dir.display()))?;
+    }
+    Repository::clone(source, &dir)
+        .with_context(|| format!("Failed to clone central hooks from {source}"))?;
+    Ok(dir)
+}
+
+// Find the repos in the scanned directory which aren't excluded by the config or `.kaminoignore`, with their
+// directory names. Fails if any directory can't be read and strict discovery is on.
+fn discover(args: &ScanArgs, config: &Config, fs: &impl Fs) -> anyhow::Result<Discovered> {
+    let ignore_path = args.dir.join(ignore::DEFAULT_FILENAME);
+    let ignore: IgnoreFile = if fs.is_file(&ignore_path) {
+        let text = fs
+            .read(&ignore_path)
+            .with_context(|| format!("Failed to read {}", ignore_path.display()))?;
+        String::from_utf8_lossy(&text)
+            .parse()
+            .with_context(|| format!("Failed to parse {}", ignore_path.display()))?
+    } else {
+        IgnoreFile::default()
+    };
+
+    // Get all dir entries in given dir
+    let dirs: Vec<PathBuf> = fs
+        .read_dir(&args.dir)
+        .with_context(|| format!("Given path is not a directory: {}", args.dir.display()))?
+        .into_iter()
+        .filter(|path| fs.is_dir(path))
+        .collect();
+
+    let mut repos = Vec::new();
+    let mut unreadable = Vec::new();
+    for dir in dirs {
+        let dir_name = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if config.is_excluded(&dir_name) || ignore.is_ignored(&dir_name) {
+            continue;
+        }
+
+        match Repository::open(&dir) {
+            Ok(repo) => repos.push((dir, dir_name, repo)),
+            // Not being a repo is expected, but a directory we can't look in might be one
+            Err(_) => {
+                if let Err(e) = fs.read_dir(&dir) {
+                    unreadable.push((dir, e));
+                }
+            }
+        }
+    }
+
+    if config.strict_discovery && !unreadable.is_empty() {
+        anyhow::bail!(
+            "Couldn't read {} directories: {}",
+            unreadable.len(),
+            examples(&unreadable)
+        );
+    }
+    Ok(Discovered { repos, unreadable })
+}
+
+// Run `f` on each item on as many threads as there are CPUs, returning the results in the same order.
+fn in_parallel<T: Send, R: Send>(items: Vec<T>, f: impl Fn(T) -> R + Sync) -> Vec<R> {
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    // The lock is only held while taking the next item
+                    while let Some((i, item)) = queue.lock().unwrap().next() {
+                        done.push((i, f(item)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// The repos found by `discover()`, and the directories it couldn't read.
+struct Discovered {
+    repos: Vec<(PathBuf, String, Repository)>,
+    unreadable: Vec<(PathBuf, io::Error)>,
+}
+
+// List the first few unreadable directories and why.
+fn examples(unreadable: &[(PathBuf, io::Error)]) -> String {
+    const SHOWN: usize = 3;
+    let mut text = unreadable
+        .iter()
+        .take(SHOWN)
+        .map(|(dir, e)| format!("{} ({e})", dir.display()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if unreadable.len() > SHOWN {
+        text.push_str(&format!(" and {} more", unreadable.len() - SHOWN));
+    }
+    text
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_repo(
+    repo: Repository,
+    config: &Config,
+    dir: PathBuf,
+    dir_name: String,
+    options: &ScanOptions,
+    progress: impl FnMut(&FetchProgress),
+    clock: &impl Clock,
+    fs: &impl Fs,
+) -> anyhow::Result<RepoReport> {
+    let mut findings = Vec::new();
+    let mut fixes = Vec::new();
+    let enabled = |check| !config.is_skipped(&dir_name, check);
+
+    let perf_config = enabled(Check::PerfConfig) || config.fix.contains(&Fix::PerfConfig);
+    let mut status_time = None;
+    if enabled(Check::Uncommitted) {
+        let start = Instant::now();
+        let mut status = kamino_core::check_status_detailed(&repo)?;
+        status_time = Some(start.elapsed());
+        if config.ignore_submodule_pointers {
+            status.submodules.clear();
+        }
+        if !status.is_empty() {
+            let mut finding = Finding::new(
+                Check::Uncommitted,
+                format!("Has uncommitted changes: {}", status_counts(&status)),
+            );
+            finding.detail = dirty_stats_lines(&kamino_core::check_dirty_stats(&repo)?);
+            findings.push(finding);
+        }
+    } else if perf_config {
+        let start = Instant::now();
+        kamino_core::check_uncommitted(&repo)?;
+        status_time = Some(start.elapsed());
+    }
+
+    // Settings which would speed up status are only worth suggesting where it is slow
+    if let Some(time) = status_time.filter(|time| perf_config && *time >= SLOW_STATUS) {
+        if config.fix.contains(&Fix::PerfConfig) {
+            let settings = kamino_core::check_perf_config(&repo).and_then(|settings| {
+                kamino_core::enable_perf_config(&repo, &settings, config.dry_run)?;
+                Ok(settings)
+            });
+            let verb = if config.dry_run { "Would set" } else { "Set" };
+            match settings {
+                Ok(settings) => {
+                    for setting in settings {
+                        fixes.push(RemediationResult::new(
+                            Fix::PerfConfig,
+                            config.dry_run,
+                            Message::new()
+                                .text(format!("{verb} "))
+                                .reference(setting.key(), None)
+                                .text(" to true to speed up status"),
+                        ));
+                    }
+                }
+                Err(e) => fixes.push(RemediationResult::failed(
+                    Fix::PerfConfig,
+                    "Set git settings to speed up status",
+                    fix_error(e),
+                )),
+            }
+        }
+
+        let settings = kamino_core::check_perf_config(&repo)?;
+        if enabled(Check::PerfConfig) && !settings.is_empty() {
+            let keys: Vec<&str> = settings.iter().map(|setting| setting.key()).collect();
+            findings.push(Finding::new(
+                Check::PerfConfig,
+                format!(
+                    "Status took {:.1}s, setting {} would speed it up (fix with --fix perf-config)",
+                    time.as_secs_f64(),
+                    keys.join(" and ")
+                ),
+            ));
+        }
+    }
+
+    // Also needed to avoid deleting branches checked out in other worktrees
+    let worktrees = if enabled(Check::Uncommitted)
+        || enabled(Check::Worktrees)
+        || config.fix.contains(&Fix::Branches)
+    {
+        kamino_core::check_worktrees(&repo)?
+    } else {
+        Vec::new()
+    };
+    for worktree in &worktrees {
+        let message = |text: &str| {
+            Message::new()
+                .text("Worktree ")
+                .reference(worktree.name.clone(), None)
+                .text(text)
+        };
+        if worktree.valid && enabled(Check::Uncommitted) {
+            let mut status =
+                kamino_core::check_status_detailed(&Repository::open(&worktree.path)?)?;
+            if config.ignore_submodule_pointers {
+                status.submodules.clear();
+            }
+            if !status.is_empty() {
+                findings.push(Finding::new(
+                    Check::Uncommitted,
+                    message(&format!(
+                        " has uncommitted changes: {}",
+                        status_counts(&status)
+                    )),
+                ));
+            }
+        }
+        if worktree.prunable && enabled(Check::Worktrees) {
+            findings.push(Finding::new(
+                Check::Worktrees,
+                message(&format!(
+                    " is missing from {} and can be pruned",
+                    worktree.path.display()
+                )),
+            ));
+        }
+        if worktree.locked && enabled(Check::Worktrees) {
+            let reason = worktree
+                .lock_reason
+                .as_ref()
+                .map(|reason| format!(": {reason}"))
+                .unwrap_or_default();
+            findings.push(Finding::new(
+                Check::Worktrees,
+                message(&format!(" is locked{reason}")),
+            ));
+        }
+    }
+
+    let mut stashes = 0;
+    let repo = {
+        // Unfortunately checking the stash takes a mut ref to the repository although
+        // it doesn't seem to actually modify anything. Since none of this program wants
+        // to modify the repo we scope the mut ref.
+        let mut repo = repo;
+        if enabled(Check::Stashed) {
+            let stashed = kamino_core::check_stashed(&mut repo)?;
+            stashes = stashed as usize;
+            if stashed > 0 {
+                let mut message = Message::new().text(format!("Has {stashed} stashed changes"));
+                if let Some(oldest) = kamino_core::oldest_stash(&repo)? {
+                    message = message.text(", the oldest from ").time(oldest);
+                }
+                let mut finding = Finding::new(Check::Stashed, message);
+                let timestamps = Timestamps::new(TimeZone::default(), clock.now());
+                finding.detail = kamino_core::check_stashes(&repo)?
+                    .iter()
+                    .map(|stash| stash_line(stash, &timestamps))
+                    .collect();
+                findings.push(finding);
+            }
+        }
+        repo
+    };
+
+    // Stashes this old are effectively lost work, so each one is reported on its own
+    if let Some(days) = config
+        .stale_stash_days
+        .filter(|_| enabled(Check::StaleStash))
+    {
+        let cutoff = clock.now() - i64::from(days) * DAY;
+        for stash in kamino_core::check_stashes(&repo)? {
+            if stash.time > cutoff {
+                continue;
+            }
+            let message = Message::new()
+                .text("Stashed change ")
+                .reference(format!("stash@{{{}}}", stash.index), None)
+                .text(format!(" ({:?}) is from ", stash.message))
+                .time(stash.time)
+                .text(format!(", over {days} days ago, and is probably forgotten"));
+            findings.push(Finding::new(Check::StaleStash, message));
+        }
+    }
+
+    if enabled(Check::State) {
+        if let Some(state) = kamino_core::check_state(&repo) {
+            let operation = match state {
+                RepositoryState::Merge => "a merge",
+                RepositoryState::Revert | RepositoryState::RevertSequence => "a revert",
+                RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+                    "a cherry-pick"
+                }
+                RepositoryState::Bisect => "a bisect",
+                RepositoryState::Rebase
+                | RepositoryState::RebaseInteractive
+                | RepositoryState::RebaseMerge => "a rebase",
+                RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => {
+                    "an am or rebase"
+                }
+                RepositoryState::Clean => "nothing",
+            };
+            findings.push(Finding::new(
+                Check::State,
+                format!("Has {operation} in progress"),
+            ));
+        }
+    }
+
+    // HEAD is often detached during a rebase or bisect, which the state check already reports
+    if enabled(Check::CheckedOut) && kamino_core::check_state(&repo).is_none() {
+        if let Some(off) = kamino_core::check_checked_out(&repo, options.get_remote()) {
+            let message = match off.branch {
+                Some(branch) => Message::new()
+                    .text("Branch ")
+                    .reference(branch, None)
+                    .text(" is checked out instead of the default branch "),
+                None => Message::new().text("HEAD is detached instead of on the default branch "),
+            };
+            findings.push(Finding::new(
+                Check::CheckedOut,
+                message.reference(off.default, None),
+            ));
+        }
+    }
+
+    if enabled(Check::Remote) {
+        let remote = options.get_remote();
+        match kamino_core::check_remote(&repo, remote)? {
+            Some(MissingRemote::NoRemotes) => findings.push(Finding::new(
+                Check::Remote,
+                "Has no remotes, so it isn't backed up anywhere",
+            )),
+            Some(MissingRemote::NotFound { available }) => findings.push(Finding::new(
+                Check::Remote,
+                Message::new()
+                    .text("Has no remote ")
+                    .reference(remote, None)
+                    .text(format!(" (only {})", available.join(", "))),
+            )),
+            None => (),
+        }
+    }
+
+    if enabled(Check::RemoteUrl) {
+        let remote = options.get_remote();
+        if let Some(url) = repo
+            .find_remote(remote)
+            .ok()
+            .and_then(|r| r.url().map(ToOwned::to_owned))
+        {
+            if !config.is_url_allowed(&url) {
+                findings.push(Finding::new(
+                    Check::RemoteUrl,
+                    Message::new()
+                        .text("Remote ")
+                        .reference(remote, None)
+                        .text(format!(" URL {url} is not allowed by policy.remoteUrl")),
+                ));
+            }
+        }
+    }
+
+    // git sees a different history with grafts or replace refs, but the counts below are of the stored one
+    let grafts = kamino_core::check_grafts(&repo, fs)?;
+    if enabled(Check::Grafts) {
+        if grafts.grafts > 0 {
+            findings.push(Finding::new(
+                Check::Grafts,
+                format!(
+                    "Has {} grafts in .git/info/grafts, which alter the history git shows",
+                    grafts.grafts
+                ),
+            ));
+        }
+        for (replaced, replacement) in &grafts.replaced {
+            findings.push(Finding::new(
+                Check::Grafts,
+                Message::new()
+                    .text("Object ")
+                    .reference(short_hash(replaced), None)
+                    .text(" is replaced by ")
+                    .reference(short_hash(replacement), None)
+                    .text(", which alters the history git shows"),
+            ));
+        }
+    }
+    let altered = if grafts.is_empty() {
+        ""
+    } else {
+        " (ignoring grafts and replace refs)"
+    };
+
+    // Remote-tracking branches gone from the remote. Their upstreams count as deleted below, even in a dry run.
+    let mut pruned = Vec::new();
+    if config.fix.contains(&Fix::Prune) {
+        let verb = if config.dry_run {
+            "Would prune"
+        } else {
+            "Pruned"
+        };
+        match kamino_core::prune_remote(&repo, options.get_remote(), config.dry_run) {
+            Ok(names) => pruned = names,
+            Err(e) => fixes.push(RemediationResult::failed(
+                Fix::Prune,
+                Message::new()
+                    .text("Prune remote-tracking branches of ")
+                    .reference(options.get_remote(), None),
+                fix_error(e),
+            )),
+        }
+        for name in &pruned {
+            fixes.push(RemediationResult::new(
+                Fix::Prune,
+                config.dry_run,
+                Message::new()
+                    .text(format!("{verb} remote-tracking branch "))
+                    .reference(name.clone(), None),
+            ));
+        }
+    }
+
+    // Shallow clones get their full history first, so that the counts below are exact
+    if config.fix.contains(&Fix::Unshallow) {
+        let message = |verb: &str| {
+            Message::new()
+                .text(format!(
+                    "{verb} the full history of this shallow clone from "
+                ))
+                .reference(options.get_remote(), None)
+        };
+        match kamino_core::unshallow(&repo, options.get_remote(), config.dry_run) {
+            Ok(true) if config.dry_run => {
+                fixes.push(RemediationResult::new(
+                    Fix::Unshallow,
+                    true,
+                    message("Would fetch"),
+                ));
+            }
+            Ok(true) => {
+                fixes.push(RemediationResult::new(
+                    Fix::Unshallow,
+                    false,
+                    message("Fetched"),
+                ));
+            }
+            Ok(false) => (),
+            Err(e) => fixes.push(RemediationResult::failed(
+                Fix::Unshallow,
+                message("Fetch"),
+                fix_error(e),
+            )),
+        }
+    }
+
+    // Checked after any unshallowing, so that a repo which was just fixed isn't reported
+    if enabled(Check::Shallow) && repo.is_shallow() {
+        findings.push(Finding::new(
+            Check::Shallow,
+            "Is a shallow clone, so history is cut off and ahead/behind counts are only lower bounds \
+             (fix with --fix unshallow)",
+        ));
+    }
+
+    let pull = config.fix.contains(&Fix::Pull);
+    let push = config.fix.contains(&Fix::Push);
+    let mut fetches = Vec::new();
+    // Remotes which didn't answer in time aren't asked anything else either, since that would stall the same way
+    let mut timed_out = Vec::new();
+    if enabled(Check::Ahead) || enabled(Check::Behind) || pull || push {
+        let fetched = kamino_core::check_ahead_behind_progress(&repo, options, progress)?;
+        fetches = fetched.fetches;
+        timed_out = fetched.timed_out;
+        if fetched.cancelled && enabled(Check::Fetch) {
+            findings.push(Finding::new(
+                Check::Fetch,
+                "Fetching was interrupted, so branches are compared as of the last fetch",
+            ));
+        }
+        if enabled(Check::Fetch) {
+            for remote in &timed_out {
+                let timeout = options.get_fetch_timeout().unwrap_or_default();
+                findings.push(Finding::new(
+                    Check::Fetch,
+                    Message::new()
+                        .text("Fetching ")
+                        .reference(remote, None)
+                        .text(format!(
+                            " timed out after {}s, so branches are compared as of the last fetch",
+                            timeout.as_secs()
+                        )),
+                ));
+            }
+        }
+        let mut results = fetched.results;
+
+        // The default branch is always compared with the remote's, even if it doesn't track it, unless --branches
+        // leaves it out. With --all-remotes, branches are already compared with the same name on every remote.
+        let head = repo.head().ok();
+        let head = head
+            .as_ref()
+            .filter(|head| head.is_branch())
+            .and_then(|head| head.shorthand());
+        let compared = |name: &str| options.get_branches().matches(name, head);
+        match kamino_core::check_default_branch(&repo, options.get_remote())? {
+            Some(DefaultBranch::Compared(ab))
+                if !ab.tracked
+                    && options.get_fetch() != FetchPolicy::AllRemotes
+                    && ab.branch_name.as_deref().is_none_or(compared) =>
+            {
+                results.push(Ok(ab));
+            }
+            Some(DefaultBranch::Missing { name, upstream })
+                if enabled(Check::Behind) && compared(&name) =>
+            {
+                findings.push(Finding::new(
+                    Check::Behind,
+                    Message::new()
+                        .text("Default branch ")
+                        .reference(name, None)
+                        .text(" is missing locally, only ")
+                        .reference(upstream, None)
+                        .text(" exists"),
+                ));
+            }
+            _ => {}
+        }
+
+        for ab in results {
+            let ab = ab?;
+
+            let upstream_url = ab.remote.as_deref().and_then(|remote| {
+                let forge = repo
+                    .find_remote(remote)
+                    .ok()
+                    .and_then(|r| r.url().and_then(Forge::from_remote_url))?;
+                let branch = ab
+                    .upstream_name
+                    .as_deref()?
+                    .strip_prefix(remote)?
+                    .strip_prefix('/')?;
+                Some(forge.branch_url(branch))
+            });
+            // Branches are shared by all worktrees, so say where this one is checked out
+            let checked_out_in = worktrees
+                .iter()
+                .find(|worktree| worktree.branch.is_some() && worktree.branch == ab.branch_name)
+                .map(|worktree| format!(" (checked out in worktree {})", worktree.name))
+                .unwrap_or_default();
+            let message = |relation: &str, count: usize| {
+                Message::new()
+                    .text("Branch ")
+                    .reference(ab.branch_name.as_deref().unwrap_or("(unnamed??)"), None)
+                    .text(format!(" is {relation} "))
+                    .reference(
+                        ab.upstream_name.as_deref().unwrap_or("upstream"),
+                        upstream_url.clone(),
+                    )
+                    .text(commit_count(count, ab.lower_bound))
+                    .text(altered)
+                    .text(checked_out_in.clone())
+            };
+            let fix_message = |verb: &str, relation: &str, count: usize| {
+                Message::new()
+                    .text(format!("{verb} branch "))
+                    .reference(ab.branch_name.as_deref().unwrap_or("(unnamed??)"), None)
+                    .text(format!(", which was {relation} "))
+                    .reference(
+                        ab.upstream_name.as_deref().unwrap_or("upstream"),
+                        upstream_url.clone(),
+                    )
+                    .text(commit_count(count, ab.lower_bound))
+            };
+
+            // Branches which are strictly ahead or behind their own upstream can be fixed, if asked to
+            // A failed fix is recorded, and the branch is still reported below as if it hadn't been attempted.
+            let fixed =
+                |action, result: Result<bool, String>, (done, would, attempt), relation, count| {
+                    let verb = if config.dry_run { would } else { done };
+                    match result {
+                        Ok(true) => Some(RemediationResult::new(
+                            action,
+                            config.dry_run,
+                            fix_message(verb, relation, count),
+                        )),
+                        Ok(false) => None,
+                        Err(e) => Some(RemediationResult::failed(
+                            action,
+                            fix_message(attempt, relation, count),
+                            e,
+                        )),
+                    }
+                };
+            let fix = match (ab.ahead, ab.behind, ab.branch_name.as_deref()) {
+                (Some(ahead), Some(0), Some(branch_name))
+                    if ahead > 0
+                        && push
+                        && ab.tracked
+                        && !options.is_cancelled()
+                        && !timed_out.iter().any(|r| Some(r) == ab.remote.as_ref()) =>
+                {
+                    fixed(
+                        Fix::Push,
+                        kamino_core::push(&repo, branch_name, config.dry_run).map_err(fix_error),
+                        ("Pushed", "Would push", "Push"),
+                        "ahead of",
+                        ahead,
+                    )
+                }
+                (Some(0), Some(behind), Some(branch_name)) if behind > 0 && pull && ab.tracked => {
+                    fixed(
+                        Fix::Pull,
+                        kamino_core::fast_forward(&repo, branch_name, config.dry_run)
+                            .map_err(fix_error),
+                        ("Fast-forwarded", "Would fast-forward", "Fast-forward"),
+                        "behind",
+                        behind,
+                    )
+                }
+                _ => None,
+            };
+            if let Some(fix) = fix {
+                let applied = fix.outcome == Outcome::Applied;
+                fixes.push(fix);
+                if applied {
+                    continue;
+                }
+            }
+
+            if let Some(ahead) = ab.ahead {
+                if ahead > 0 && enabled(Check::Ahead) {
+                    findings.push(Finding::new(Check::Ahead, message("ahead of", ahead)));
+                }
+            }
+
+            if let Some(behind) = ab.behind {
+                if behind > 0 && enabled(Check::Behind) {
+                    findings.push(Finding::new(Check::Behind, message("behind", behind)));
+                }
+            }
+        }
+    }
+
+    // After fetching, so that a remote-tracking base is up to date
+    if let Some(base) = config.base.as_deref().filter(|_| enabled(Check::Base)) {
+        match kamino_core::check_ahead_behind_base(&repo, base) {
+            Ok(results) => {
+                let head = repo.head().ok();
+                let head = head
+                    .as_ref()
+                    .filter(|head| head.is_branch())
+                    .and_then(|head| head.shorthand());
+                for ab in results {
+                    let ab = ab?;
+                    let (name, ahead, behind) = match (ab.branch_name, ab.ahead, ab.behind) {
+                        (Some(name), Some(ahead), Some(behind)) if behind > 0 => {
+                            (name, ahead, behind)
+                        }
+                        _ => continue,
+                    };
+                    if !options.get_branches().matches(&name, head) {
+                        continue;
+                    }
+                    let mut message = Message::new()
+                        .text("Branch ")
+                        .reference(name, None)
+                        .text(" is behind ")
+                        .reference(base, None)
+                        .text(commit_count(behind, ab.lower_bound));
+                    if ahead > 0 {
+                        message = message.text(format!(
+                            ", and ahead{}",
+                            commit_count(ahead, ab.lower_bound)
+                        ));
+                    }
+                    findings.push(Finding::new(Check::Base, message));
+                }
+            }
+            Err(BaseError::NotFound(..)) => findings.push(Finding::new(
+                Check::Base,
+                Message::new()
+                    .text("Base ")
+                    .reference(base, None)
+                    .text(" to compare branches with doesn't exist"),
+            )),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    // After fetching, so that a fetch which worked counts
+    if enabled(Check::FetchAge) {
+        findings.extend(fetch_age_finding(&repo, config, clock.now(), fs));
+    }
+
+    if enabled(Check::Lfs) {
+        let issues = kamino_core::check_lfs(&repo, fs)?;
+        for (problem, what, fix) in [
+            (
+                LfsProblem::NotFetched,
+                "content was never fetched",
+                "git lfs pull",
+            ),
+            (
+                LfsProblem::NotCheckedOut,
+                "content wasn't checked out",
+                "git lfs checkout",
+            ),
+        ] {
+            let paths: Vec<&Path> = issues
+                .iter()
+                .filter(|issue| issue.problem == problem)
+                .map(|issue| issue.path.as_path())
+                .collect();
+            let (files, is) = match paths.len() {
+                0 => continue,
+                1 => (String::from("LFS file "), " is only a pointer because its"),
+                n => (
+                    format!("{n} LFS files, e.g. "),
+                    ", are only pointers because their",
+                ),
+            };
+            findings.push(Finding::new(
+                Check::Lfs,
+                Message::new()
+                    .text(files)
+                    .reference(paths[0].display().to_string(), None)
+                    .text(format!("{is} {what} (fix with `{fix}`)")),
+            ));
+        }
+    }
+
+    if enabled(Check::LargeFiles) {
+        let large = kamino_core::check_large_files(&repo, config.large_file_threshold)?;
+        if let Some(largest) = large.first() {
+            let path = largest.path.display().to_string();
+            let message = if large.len() == 1 {
+                Message::new()
+                    .text("Tracked file ")
+                    .reference(path, None)
+                    .text(format!(" is large ({})", format_bytes(largest.size)))
+            } else {
+                let total = large.iter().map(|file| file.size).sum();
+                Message::new()
+                    .text(format!(
+                        "{} large tracked files, {} in total, the largest ",
+                        large.len(),
+                        format_bytes(total)
+                    ))
+                    .reference(path, None)
+                    .text(format!(" ({})", format_bytes(largest.size)))
+            };
+            findings.push(Finding::new(Check::LargeFiles, message));
+        }
+    }
+
+    if enabled(Check::TrackedIgnored) {
+        // A few are named, which is usually enough to see what the ignore rule was for
+        const SHOWN: usize = 3;
+        let ignored = kamino_core::check_tracked_ignored(&repo)?;
+        if !ignored.is_empty() {
+            let mut message = if ignored.len() == 1 {
+                Message::new().text("Tracked file ")
+            } else {
+                Message::new().text(format!("{} tracked files are ignored: ", ignored.len()))
+            };
+            for (i, path) in ignored.iter().take(SHOWN).enumerate() {
+                if i > 0 {
+                    message = message.text(", ");
+                }
+                message = message.reference(path.display().to_string(), None);
+            }
+            if ignored.len() > SHOWN {
+                message = message.text(format!(" and {} more", ignored.len() - SHOWN));
+            }
+            if ignored.len() == 1 {
+                message = message.text(" is ignored");
+            }
+            findings.push(Finding::new(
+                Check::TrackedIgnored,
+                message.text(" (stop tracking with `git rm --cached`, or change .gitignore)"),
+            ));
+        }
+    }
+
+    let reachable = !options.is_cancelled() && !timed_out.iter().any(|r| r == options.get_remote());
+
+    // Asking the remote needs the network, so this is skipped along with fetching
+    if enabled(Check::RemoteHead) && options.get_fetch() != FetchPolicy::Never && reachable {
+        let remote = options.get_remote();
+        if let Some(change) = kamino_core::check_remote_head(&repo, remote)? {
+            findings.push(Finding::new(
+                Check::RemoteHead,
+                Message::new()
+                    .text("Default branch of ")
+                    .reference(remote, None)
+                    .text(" changed from ")
+                    .reference(change.cached, None)
+                    .text(" to ")
+                    .reference(change.current, None)
+                    .text(format!(
+                        ", update with `git remote set-head {remote} --auto`"
+                    )),
+            ));
+        }
+    }
+
+    if enabled(Check::Tags) && reachable {
+        for tag in kamino_core::check_tags(&repo, options.get_remote())? {
+            findings.push(Finding::new(
+                Check::Tags,
+                Message::new()
+                    .text("Tag ")
+                    .reference(tag, None)
+                    .text(" only exists locally"),
+            ));
+        }
+    }
+
+    let delete_branches = config.fix.contains(&Fix::Branches);
+    if enabled(Check::Merged) || delete_branches {
+        let remote = options.get_remote();
+        for mut branch in kamino_core::check_merged(&repo, remote)? {
+            if let Some(upstream) = &branch.upstream {
+                branch.upstream_gone |= pruned.contains(upstream);
+            }
+
+            // Merged branches whose upstream was deleted are finished with, unless checked out somewhere
+            let checked_out = worktrees
+                .iter()
+                .any(|worktree| worktree.branch.as_deref() == Some(branch.name.as_str()));
+            if delete_branches && branch.upstream_gone && !checked_out {
+                let message = |verb: &str| {
+                    Message::new()
+                        .text(format!("{verb} branch "))
+                        .reference(branch.name.clone(), None)
+                        .text(", which was merged into ")
+                        .reference(branch.merged_into.clone(), None)
+                        .text(" and its upstream branch was deleted")
+                };
+                let fix = if config.dry_run {
+                    RemediationResult::new(Fix::Branches, true, message("Would delete"))
+                } else {
+                    match repo
+                        .find_branch(&branch.name, git2::BranchType::Local)
+                        .and_then(|mut local| local.delete())
+                    {
+                        Ok(()) => RemediationResult::new(Fix::Branches, false, message("Deleted")),
+                        Err(e) => RemediationResult::failed(
+                            Fix::Branches,
+                            message("Delete"),
+                            fix_error(e),
+                        ),
+                    }
+                };
+                let applied = fix.outcome == Outcome::Applied;
+                fixes.push(fix);
+                if applied {
+                    continue;
+                }
+            }
+
+            if !enabled(Check::Merged) || (config.merged_gone_only && !branch.upstream_gone) {
+                continue;
+            }
+            let gone = if branch.upstream_gone {
+                " and its upstream branch was deleted"
+            } else {
+                ""
+            };
+            findings.push(Finding::new(
+                Check::Merged,
+                Message::new()
+                    .text("Branch ")
+                    .reference(branch.name, None)
+                    .text(" is merged into ")
+                    .reference(branch.merged_into, None)
+                    .text(gone),
+            ));
+        }
+    }
+
+    if enabled(Check::Submodules) {
+        for submodule in kamino_core::check_submodules(&repo)? {
+            let path = submodule.path.display().to_string();
+            let message = |text: &str| {
+                Message::new()
+                    .text("Submodule ")
+                    .reference(path.clone(), None)
+                    .text(text)
+            };
+            if submodule.uninitialized {
+                findings.push(Finding::new(
+                    Check::Submodules,
+                    message(" is not initialized"),
+                ));
+            }
+            if submodule.dirty {
+                findings.push(Finding::new(
+                    Check::Submodules,
+                    message(" has uncommitted changes"),
+                ));
+            }
+            if let (true, Some(checked_out), Some(recorded)) = (
+                submodule.wrong_commit(),
+                submodule.checked_out,
+                submodule.recorded,
+            ) {
+                findings.push(Finding::new(
+                    Check::Submodules,
+                    message(&format!(
+                        " is at {:.7} but the superproject records {:.7}",
+                        checked_out.to_string(),
+                        recorded.to_string()
+                    )),
+                ));
+            }
+        }
+    }
+
+    if enabled(Check::Nested) {
+        for nested in kamino_core::check_nested_repos(&repo)? {
+            let message = Message::new()
+                .text("Repo ")
+                .reference(nested.path.display().to_string(), None);
+            let message = match nested.kind {
+                NestedKind::Untracked => message.text(
+                    " is nested in the working tree, so its files are never committed here \
+                     (add it as a submodule, or ignore it)",
+                ),
+                NestedKind::Embedded => message.text(
+                    " is committed as a gitlink but isn't in .gitmodules, so clones can't check it out",
+                ),
+            };
+            findings.push(Finding::new(Check::Nested, message));
+        }
+    }
+
+    // Paths are shown relative to the working directory, e.g. ".git/hooks"
+    let shown = |path: &Path| {
+        let relative = repo
+            .workdir()
+            .and_then(|workdir| path.strip_prefix(workdir).ok())
+            .unwrap_or(path);
+        relative.display().to_string()
+    };
+    let hook_sources = options.get_hook_sources();
+    let active = shown(&kamino_core::active_hooks_dir(&repo));
+    if config.fix.contains(&Fix::Hooks) {
+        let verb = if config.dry_run {
+            "Would copy"
+        } else {
+            "Copied"
+        };
+        let from = hook_sources.join(" or ");
+        match kamino_core::sync_hooks_in(&repo, hook_sources, fs, config.dry_run) {
+            Ok(names) => {
+                for name in names {
+                    fixes.push(RemediationResult::new(
+                        Fix::Hooks,
+                        config.dry_run,
+                        Message::new()
+                            .text(format!("{verb} hook "))
+                            .reference(format!("{name:?}"), None)
+                            .text(format!(" from {from} to {active}")),
+                    ));
+                }
+            }
+            Err(e) => fixes.push(RemediationResult::failed(
+                Fix::Hooks,
+                format!("Copy hooks from {from} to {active}"),
+                fix_error(e),
+            )),
+        }
+    }
+
+    if enabled(Check::Hooks) {
+        let in_repo = hook_sources.join(" or ");
+        // The hook's name is a reference, so that it can be picked out of the message, e.g. for CSV
+        let hook = |name: &OsStr| {
+            Message::new()
+                .text("Hook ")
+                .reference(format!("{name:?}"), None)
+        };
+        let mut diffs = if config.diff {
+            kamino_core::diff_hooks_in(&repo, hook_sources, fs)?
+        } else {
+            Vec::new()
+        };
+        for found in kamino_core::check_hooks_in(&repo, hook_sources, fs)? {
+            if !found.executable {
+                findings.push(Finding::new(
+                    Check::Hooks,
+                    hook(&found.name).text(format!(
+                        " in {active} isn't executable, so git never runs it"
+                    )),
+                ));
+            }
+            let message = hook(&found.name);
+            let message = match found.state {
+                HookState::ActiveOnly => message.text(format!(" only appears in {active}")),
+                HookState::InRepoOnly => message.text(format!(" only appears in {in_repo}")),
+                HookState::Mismatch => {
+                    message.text(format!(" is different in {active} and {in_repo}"))
+                }
+                HookState::Conflict => {
+                    message.text(format!(" is different in {}", hook_sources.join(" and ")))
+                }
+                HookState::BadSymlink => message.text(format!(
+                    " in {active} is a symlink which is broken or doesn't point at the one in {in_repo}"
+                )),
+                HookState::Good => continue,
+                // A state this version of kamino doesn't know how to describe
+                _ => message.text(format!(" isn't as expected in {active}")),
+            };
+            let mut finding = Finding::new(Check::Hooks, message);
+            if let Some(i) = diffs.iter().position(|diff| diff.name == found.name) {
+                let diff = diffs.remove(i);
+                finding.detail = diff.unified().lines().map(ToOwned::to_owned).collect();
+            }
+            findings.push(finding);
+        }
+
+        for issue in kamino_core::check_hook_shebangs(&repo, hook_sources, fs)? {
+            let problem = match issue.problem {
+                ShebangProblem::Missing => String::from("doesn't start with a #! line"),
+                ShebangProblem::CrLf => String::from("has a CRLF line ending on its #! line"),
+                ShebangProblem::InterpreterNotFound(interpreter) => {
+                    format!("uses interpreter \"{interpreter}\" which isn't installed")
+                }
+            };
+            findings.push(Finding::new(
+                Check::Hooks,
+                hook(&issue.name).text(format!(" in {} {problem}", issue.dir.display())),
+            ));
+        }
+
+        if let Some(central) = options.get_central_hooks() {
+            for drift in kamino_core::check_hooks_central(&repo, hook_sources, central, fs)? {
+                let message = hook(&drift.name).text(match drift.drift {
+                    Drift::Missing => format!(
+                        " from the central hooks is missing from {}",
+                        drift.dir.display()
+                    ),
+                    Drift::Different => format!(
+                        " in {} is different from the central version",
+                        drift.dir.display()
+                    ),
+                });
+                findings.push(Finding::new(Check::Hooks, message));
+            }
+        }
+    }
+
+    // Commits aren't made in bare repos, so they don't need an identity
+    if enabled(Check::Identity) && !repo.is_bare() {
+        let identity = kamino_core::check_identity(&repo)?;
+        for (key, value, expected) in [
+            ("user.name", &identity.name, config.identity_name.as_ref()),
+            ("user.email", &identity.email, config.email_for(&dir_name)),
+        ] {
+            let message = match (value, expected) {
+                (None, _) => Message::new()
+                    .reference(key, None)
+                    .text(" isn't set, so git would guess it when committing"),
+                (Some(value), Some(expected)) if !expected.matches(value) => Message::new()
+                    .text("Commits would be made with ")
+                    .reference(key, None)
+                    .text(format!(" \"{value}\", which doesn't match \"{expected}\"")),
+                (Some(_), _) => continue,
+            };
+            findings.push(Finding::new(Check::Identity, message));
+        }
+    }
+
+    // Only repos which are meant to sign commits are checked, since most don't
+    let signing = !config.signing_keys.is_empty()
+        || repo
+            .config()
+            .and_then(|c| c.get_bool("commit.gpgSign"))
+            .unwrap_or(false);
+    if enabled(Check::Signatures) && signing {
+        const SHOWN: usize = 3;
+        let issues = kamino_core::check_signatures(&repo, &config.signing_keys)?;
+        let mut groups: Vec<(String, SignatureProblem, Vec<String>)> = Vec::new();
+        for issue in issues {
+            let hash = short_hash(&issue.commit).to_owned();
+            match groups
+                .iter_mut()
+                .find(|(branch, problem, _)| *branch == issue.branch && *problem == issue.problem)
+            {
+                Some((_, _, hashes)) => hashes.push(hash),
+                None => groups.push((issue.branch, issue.problem, vec![hash])),
+            }
+        }
+        for (branch, problem, hashes) in groups {
+            let what = match problem {
+                SignatureProblem::Unsigned => String::from("which aren't signed"),
+                SignatureProblem::Bad => String::from("with bad signatures"),
+                SignatureProblem::UnexpectedKey(key) => format!("signed with unexpected key {key}"),
+                SignatureProblem::Unverified => {
+                    String::from("whose signatures couldn't be verified")
+                }
+            };
+            let mut listed = hashes[..hashes.len().min(SHOWN)].join(", ");
+            if hashes.len() > SHOWN {
+                listed.push_str(&format!(" and {} more", hashes.len() - SHOWN));
+            }
+            findings.push(Finding::new(
+                Check::Signatures,
+                Message::new()
+                    .text("Branch ")
+                    .reference(branch, None)
+                    .text(format!(
+                        " has {} unpushed commits {what} ({listed})",
+                        hashes.len()
+                    )),
+            ));
+        }
+    }
+
+    if config.fix.contains(&Fix::Permissions) {
+        let verb = if config.dry_run {
+            "Would stop"
+        } else {
+            "Stopped"
+        };
+        let fixed = kamino_core::check_permissions(&repo, fs)
+            .and_then(|issues| kamino_core::fix_permissions(&issues, fs, config.dry_run));
+        match fixed {
+            Ok(paths) => {
+                for path in paths {
+                    fixes.push(RemediationResult::new(
+                        Fix::Permissions,
+                        config.dry_run,
+                        Message::new()
+                            .text(format!("{verb} every user being able to write to "))
+                            .reference(shown(&path), None),
+                    ));
+                }
+            }
+            Err(e) => fixes.push(RemediationResult::failed(
+                Fix::Permissions,
+                "Stop every user being able to write to .git and its hooks",
+                fix_error(e),
+            )),
+        }
+    }
+
+    if enabled(Check::Permissions) {
+        for issue in kamino_core::check_permissions(&repo, fs)? {
+            let message = Message::new().reference(shown(&issue.path), None);
+            let message = match issue.problem {
+                PermissionProblem::WorldWritable => message.text(
+                    " can be written to by every user, who could add hooks that run as you \
+                     (fix with --fix permissions)",
+                ),
+                PermissionProblem::OtherOwner { uid } => message.text(format!(
+                    " is owned by another user (uid {uid}), who could change what it runs as you"
+                )),
+            };
+            findings.push(Finding::new(Check::Permissions, message));
+        }
+    }
+
+    let now = clock.now();
+    let activity = kamino_core::check_activity(&repo)?;
+
+    if let Some(after) = config.stale_after.filter(|_| enabled(Check::Stale)) {
+        let idle = |time: i64| u64::try_from(now - time).is_ok_and(|age| age >= after.as_secs());
+        // With --stale-activity, a repo which is still being pulled or checked out isn't stale
+        let active = config.stale_activity && activity.is_some_and(|activity| !idle(activity.last));
+        if let Some(last) = kamino_core::last_commit(&repo)?.filter(|&last| idle(last) && !active) {
+            let mut message = Message::new().text("No commits since ").time(last);
+            if let Some(activity) = activity.filter(|_| config.stale_activity) {
+                message = message.text(", and no activity since ").time(activity.last);
+            }
+            findings.push(Finding::new(Check::Stale, message));
+        }
+    }
+
+    let stage = activity.map(|activity| config.lifecycle.classify(&activity, now));
+
+    let mut summary = summarize(&findings, stashes);
+    // Walking the history is slow, so only bother where something is known to be unpushed
+    if summary.uncommitted + summary.ahead + summary.stashes > 0 {
+        summary.at_risk = kamino_core::estimate_at_risk(&repo)?;
+    }
+
+    Ok(RepoReport {
+        name: dir_name,
+        path: dir,
+        findings,
+        fixes,
+        stage,
+        fetches,
+        summary,
+    })
+}
+
+// How long checking for uncommitted changes can take before the perf-config check suggests settings to speed it up.
+const SLOW_STATUS: Duration = Duration::from_secs(1);
+
+// Describe an error from a fix along with its causes, so that the record of it says what went wrong.
+fn fix_error(error: impl std::error::Error + Send + Sync + 'static) -> String {
+    format!("{:#}", anyhow::Error::new(error))
+}
+
+// Describe a stash for the lines under the stashed finding, e.g. `stash 0: "wip parser" (3 months ago, branch
+// feature/x)`.
+fn stash_line(stash: &Stash, timestamps: &Timestamps) -> String {
+    let branch = match &stash.branch {
+        Some(branch) => format!("branch {branch}"),
+        None => String::from("detached HEAD"),
+    };
+    format!(
+        "stash {}: {:?} ({}, {branch})",
+        stash.index,
+        stash.message,
+        timestamps.relative(stash.time)
+    )
+}
+
+// How many changed files are listed under the uncommitted finding.
+const LARGEST_CHANGES: usize = 3;
+
+// Summarize how much uncommitted changes add up to, like `git diff --shortstat`, followed by the most changed files.
+fn dirty_stats_lines(stats: &DirtyStats) -> Vec<String> {
+    if stats.files_changed == 0 {
+        return Vec::new();
+    }
+    let plural = |count: usize, one: &str, many: &str| {
+        format!("{count} {}", if count == 1 { one } else { many })
+    };
+    let mut lines = vec![format!(
+        "{}, {}, {}",
+        plural(stats.files_changed, "file changed", "files changed"),
+        plural(stats.insertions, "insertion(+)", "insertions(+)"),
+        plural(stats.deletions, "deletion(-)", "deletions(-)"),
+    )];
+    lines.extend(stats.files.iter().take(LARGEST_CHANGES).map(|file| {
+        format!(
+            "  {} +{} -{}",
+            file.path.display(),
+            file.insertions,
+            file.deletions
+        )
+    }));
+    lines
+}
+
+// Check just what `check_current_branch()` can tell cheaply, for --quick.
+fn quick_check_repo(
+    repo: &Repository,
+    config: &Config,
+    dir: PathBuf,
+    dir_name: String,
+) -> anyhow::Result<RepoReport> {
+    let mut findings = Vec::new();
+    let enabled = |check| !config.is_skipped(&dir_name, check);
+    let current =
+        kamino_core::check_current_branch(repo, &kamino_core::CurrentBranchOptions::default())?;
+
+    if current.uncommitted && enabled(Check::Uncommitted) {
+        findings.push(Finding::new(Check::Uncommitted, "Has uncommitted changes"));
+    }
+    if let Some(ab) = current.ahead_behind {
+        let last_fetch = kamino_core::last_fetch(repo, &RealFs);
+        let message = |relation: &str, count: usize| {
+            let message = Message::new()
+                .text("Branch ")
+                .reference(ab.branch_name.as_deref().unwrap_or("(unnamed??)"), None)
+                .text(format!(" is {relation} "))
+                .reference(ab.upstream_name.as_deref().unwrap_or("upstream"), None)
+                .text(commit_count(count, ab.lower_bound));
+            // Without a fetch, this is only as up to date as the last one
+            match last_fetch {
+                Some(time) => message
+                    .text(" (as of the last fetch, ")
+                    .time(time)
+                    .text(")"),
+                None => message,
+            }
+        };
+        match ab.ahead {
+            Some(ahead) if ahead > 0 && enabled(Check::Ahead) => {
+                findings.push(Finding::new(Check::Ahead, message("ahead of", ahead)));
+            }
+            _ => (),
+        }
+        match ab.behind {
+            Some(behind) if behind > 0 && enabled(Check::Behind) => {
+                findings.push(Finding::new(Check::Behind, message("behind", behind)));
+            }
+            _ => (),
+        }
+    }
+    if enabled(Check::FetchAge) {
+        findings.extend(fetch_age_finding(repo, config, SystemClock.now(), &RealFs));
+    }
+
+    Ok(RepoReport {
+        name: dir_name,
+        path: dir,
+        summary: summarize(&findings, 0),
+        findings,
+        fixes: Vec::new(),
+        stage: None,
+        fetches: Vec::new(),
+    })
+}
+
+// Report when the repo was last fetched if that was longer ago than `kamino.maxFetchAge`, or that it never was if
+// it has remotes to fetch from.
+fn fetch_age_finding(
+    repo: &Repository,
+    config: &Config,
+    now: i64,
+    fs: &impl Fs,
+) -> Option<Finding> {
+    let max_age = i64::try_from(config.max_fetch_age?.as_secs()).unwrap_or(i64::MAX);
+    match kamino_core::last_fetch(repo, fs) {
+        Some(fetched) if now - fetched <= max_age => None,
+        Some(fetched) => Some(Finding::new(
+            Check::FetchAge,
+            Message::new().text("Not fetched since ").time(fetched),
+        )),
+        None if repo.remotes().is_ok_and(|remotes| !remotes.is_empty()) => {
+            Some(Finding::new(Check::FetchAge, "Has never been fetched"))
+        }
+        None => None,
+    }
+}
+
+// How far a branch is ahead or behind, e.g. " by 3 commits". In a shallow clone the count is only a lower bound.
+fn commit_count(count: usize, lower_bound: bool) -> String {
+    if lower_bound {
+        format!(" by at least {count} commits (lower bound, shallow clone)")
+    } else {
+        format!(" by {count} commits")
+    }
+}
+
+// Abbreviate a commit hash as git does by default.
+fn short_hash(hash: &str) -> &str {
+    hash.get(..7).unwrap_or(hash)
+}
+
+// Count up a repo's findings for the summary.
+fn summarize(findings: &[Finding], stashes: usize) -> kamino_core::Summary {
+    let count = |check| findings.iter().filter(|f| f.check == check).count();
+    kamino_core::Summary {
+        repos: 1,
+        clean: usize::from(findings.is_empty()),
+        uncommitted: usize::from(count(Check::Uncommitted) > 0),
+        stashes,
+        ahead: count(Check::Ahead),
+        behind: count(Check::Behind),
+        hook_problems: count(Check::Hooks),
+        at_risk: 0,
+    }
+}
+
+// Describe uncommitted changes, e.g. "3 modified, 2 untracked".
+fn status_counts(status: &kamino_core::StatusDetails) -> String {
+    let counts: Vec<String> = [
+        (status.staged.len(), "staged"),
+        (status.modified.len(), "modified"),
+        (status.renamed.len(), "renamed"),
+        (status.untracked.len(), "untracked"),
+        (status.submodules.len(), "submodule pointers changed"),
+    ]
+    .into_iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, kind)| format!("{count} {kind}"))
+    .collect();
+    counts.join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use git2::Signature;
+    use tempfile::TempDir;
+
+    fn commit_file(dir: &Path, name: &str) {
+        let repo = Repository::init(dir).unwrap();
+        fs::write(dir.join(name), "contents").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "first", &tree, &[])
+            .unwrap();
+    }
+
+    #[test]
+    fn broken_repo() {
+        let root = TempDir::new().unwrap();
+        let (good, broken) = (root.path().join("good"), root.path().join("broken"));
+        commit_file(&good, "file");
+        commit_file(&broken, "file");
+        fs::write(broken.join("new"), "uncommitted").unwrap();
+        let history_path = root.path().join(history::HISTORY_FILENAME);
+
+        let args = Args::parse_from(["kamino", "--color", "never", root.path().to_str().unwrap()]);
+        let scan_once = || {
+            let mut output = Output::capture(Renderer::new(&args.render));
+            let code = scan(&args.scan, &args.document, &mut output).unwrap();
+            (code, output.into_captured())
+        };
+        let (code, _) = scan_once();
+        assert_eq!(code, ExitCode::SUCCESS);
+        let history = fs::read_to_string(&history_path).unwrap();
+        assert!(history.contains("uncommitted"));
+
+        // A corrupt index stops the repo being checked, but not the scan
+        fs::write(broken.join(".git").join("index"), "not an index").unwrap();
+        let (code, text) = scan_once();
+        assert_eq!(code, ExitCode::from(EXIT_ERROR));
+        assert!(text.contains("Couldn't be checked"), "{text}");
+        assert!(text.contains("Summary: 2 repos scanned"), "{text}");
+        let summary = format!("1 repos couldn't be checked: {}", broken.display());
+        assert!(text.contains(&summary), "{text}");
+        // Nor does it forget when the repo's findings were first seen
+        assert_eq!(fs::read_to_string(&history_path).unwrap(), history);
+    }
+
+    #[test]
+    fn unreadable_cache() {
+        let root = TempDir::new().unwrap();
+        commit_file(&root.path().join("repo"), "file");
+        let cache_path = root.path().join(cache::CACHE_FILENAME);
+        fs::write(&cache_path, "not a cache\n").unwrap();
+
+        // Checked from scratch, and the cache replaced with one that can be read
+        let dir = root.path().to_str().unwrap();
+        let args = Args::parse_from(["kamino", "--cache-ttl", "1h", dir]);
+        let mut output = Output::capture(Renderer::new(&args.render));
+        let code = scan(&args.scan, &args.document, &mut output).unwrap();
+        assert_eq!(code, ExitCode::SUCCESS);
+        let cache = Cache::load(&cache_path).unwrap();
+        assert!(cache != Cache::default());
+    }
+}
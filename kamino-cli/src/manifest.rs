@@ -0,0 +1,229 @@
+use crate::lifecycle::Stage;
+use std::{fmt::Write, fs, io, path::Path};
+
+/// Name of the manifest file kept in the scanned directory.
+pub const DEFAULT_FILENAME: &str = ".kaminomanifest";
+
+/// A repo recorded in the manifest.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Entry {
+    /// Directory name of the repo.
+    pub name: String,
+    /// URL of the repo's remote, if it has one.
+    pub url: Option<String>,
+    /// Lifecycle stage of the repo, if it has any activity to go by.
+    pub stage: Option<Stage>,
+}
+
+/// The repos which were in the scanned directory at some point, to compare against later.
+///
+/// Stored as one repo per line, with tab-separated fields: the directory name, the remote URL and the lifecycle stage
+/// (each `-` if there isn't one). Manifests saved before stages were recorded have no stage field.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Manifest(Vec<Entry>);
+
+/// A difference between two manifests.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Change {
+    Added(Entry),
+    Removed(Entry),
+    /// A repo disappeared and one with the same remote URL appeared, so it was probably moved or renamed.
+    Moved {
+        from: String,
+        to: String,
+        url: String,
+    },
+    /// A repo moved to another lifecycle stage, e.g. from active to stale.
+    Staged {
+        name: String,
+        from: Stage,
+        to: Stage,
+    },
+}
+
+impl Manifest {
+    pub fn new(mut entries: Vec<Entry>) -> Self {
+        entries.sort();
+        Self(entries)
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.0
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let entries = text
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let name = fields.next().unwrap_or_default();
+                let url = fields.next().unwrap_or("-");
+                let stage = fields.next().unwrap_or("-");
+                Entry {
+                    name: name.to_owned(),
+                    url: (url != "-").then(|| url.to_owned()),
+                    stage: Stage::ALL.into_iter().find(|s| s.as_str() == stage),
+                }
+            })
+            .collect();
+        Ok(Self::new(entries))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::from("# Repos seen by kamino, compared by `kamino diff`\n");
+        for entry in &self.0 {
+            let _ = writeln!(
+                text,
+                "{}\t{}\t{}",
+                entry.name,
+                entry.url.as_deref().unwrap_or("-"),
+                entry.stage.map_or("-", Stage::as_str)
+            );
+        }
+        fs::write(path, text)
+    }
+
+    /// What changed to get from `self` to `new`. A removed and an added repo with the same remote URL are
+    /// reported as a move rather than separately. A repo in both which moved to another lifecycle stage is reported
+    /// as such, unless either doesn't know its stage.
+    pub fn diff(&self, new: &Self) -> Vec<Change> {
+        let mut removed: Vec<&Entry> = self
+            .0
+            .iter()
+            .filter(|old| !new.0.iter().any(|e| e.name == old.name))
+            .collect();
+        let mut changes = Vec::new();
+
+        for entry in new
+            .0
+            .iter()
+            .filter(|e| !self.0.iter().any(|old| old.name == e.name))
+        {
+            let moved_from = entry.url.as_ref().and_then(|url| {
+                let i = removed
+                    .iter()
+                    .position(|old| old.url.as_ref() == Some(url))?;
+                Some(removed.remove(i))
+            });
+            changes.push(match moved_from {
+                Some(old) => Change::Moved {
+                    from: old.name.clone(),
+                    to: entry.name.clone(),
+                    url: entry.url.clone().unwrap_or_default(),
+                },
+                None => Change::Added(entry.clone()),
+            });
+        }
+        changes.extend(removed.into_iter().cloned().map(Change::Removed));
+        for entry in &new.0 {
+            let old = self.0.iter().find(|old| old.name == entry.name);
+            if let Some((from, to)) = old.and_then(|old| old.stage.zip(entry.stage)) {
+                if from != to {
+                    changes.push(Change::Staged {
+                        name: entry.name.clone(),
+                        from,
+                        to,
+                    });
+                }
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(name: &str, url: Option<&str>) -> Entry {
+        Entry {
+            name: name.into(),
+            url: url.map(Into::into),
+            stage: None,
+        }
+    }
+
+    fn staged(name: &str, stage: Option<Stage>) -> Entry {
+        Entry {
+            stage,
+            ..entry(name, None)
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let old = Manifest::new(vec![
+            entry("kamino", Some("git@github.com:schteve/kamino.git")),
+            entry("scratch", None),
+            entry("old", Some("https://example.com/old.git")),
+        ]);
+        let new = Manifest::new(vec![
+            entry("kamino", Some("git@github.com:schteve/kamino.git")),
+            entry("renamed", Some("https://example.com/old.git")),
+            entry("fresh", None),
+        ]);
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                Change::Added(entry("fresh", None)),
+                Change::Moved {
+                    from: "old".into(),
+                    to: "renamed".into(),
+                    url: "https://example.com/old.git".into()
+                },
+                Change::Removed(entry("scratch", None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn stage_transitions() {
+        let old = Manifest::new(vec![
+            staged("idle", Some(Stage::Active)),
+            staged("revived", Some(Stage::Archived)),
+            staged("same", Some(Stage::Stale)),
+            staged("unknown", None),
+        ]);
+        let new = Manifest::new(vec![
+            staged("idle", Some(Stage::Stale)),
+            staged("revived", Some(Stage::Active)),
+            staged("same", Some(Stage::Stale)),
+            staged("unknown", Some(Stage::New)),
+        ]);
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                Change::Staged {
+                    name: "idle".into(),
+                    from: Stage::Active,
+                    to: Stage::Stale
+                },
+                Change::Staged {
+                    name: "revived".into(),
+                    from: Stage::Archived,
+                    to: Stage::Active
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn save_load() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(DEFAULT_FILENAME);
+        let manifest = Manifest::new(vec![
+            entry("b", None),
+            entry("a", Some("https://example.com/a.git")),
+            staged("c", Some(Stage::Archived)),
+        ]);
+        manifest.save(&path).unwrap();
+        assert_eq!(Manifest::load(&path).unwrap(), manifest);
+
+        // Manifests from before stages were recorded
+        fs::write(&path, "a\thttps://example.com/a.git\nb\t-\n").unwrap();
+        let old = Manifest::load(&path).unwrap();
+        assert!(old.entries().iter().all(|entry| entry.stage.is_none()));
+    }
+}
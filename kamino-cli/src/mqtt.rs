@@ -0,0 +1,238 @@
+use crate::{
+    findings::{Check, RepoReport},
+    json::Json,
+    render::Timestamps,
+};
+use anyhow::Context;
+use rumqttc::{Client, Event, MqttOptions, Outgoing, Packet, QoS};
+use std::time::Duration;
+
+/// Default port for unencrypted MQTT.
+const DEFAULT_PORT: u16 = 1883;
+
+/// Build the messages to publish: a summary of the whole scan on `<topic>/summary`, and the health of each repo on
+/// `<topic>/repo/<name>`. Payloads are flat JSON objects so that Home Assistant sensors can pick values out with a
+/// `value_template` such as `{{ value_json.unpushed }}`. Times are in ISO 8601.
+pub fn messages(
+    topic: &str,
+    reports: &[RepoReport],
+    timestamps: &Timestamps,
+) -> Vec<(String, String)> {
+    let count = |check: Check| {
+        reports
+            .iter()
+            .filter(|report| report.findings.iter().any(|f| f.check == check))
+            .count()
+    };
+
+    let summary = Json::object([
+        ("repos", Json::from(reports.len())),
+        (
+            "repos_with_findings",
+            Json::from(reports.iter().filter(|r| !r.findings.is_empty()).count()),
+        ),
+        (
+            "findings",
+            Json::from(reports.iter().map(|r| r.findings.len()).sum::<usize>()),
+        ),
+        ("uncommitted", Json::from(count(Check::Uncommitted))),
+        ("unpushed", Json::from(count(Check::Ahead))),
+        ("behind", Json::from(count(Check::Behind))),
+    ]);
+
+    let mut messages = vec![(format!("{topic}/summary"), summary.to_string())];
+    for report in reports {
+        let findings: Vec<Json> = report
+            .findings
+            .iter()
+            .map(|finding| {
+                Json::object([
+                    ("check", Json::from(finding.check.as_str())),
+                    ("level", Json::from(finding.level.as_str())),
+                    ("message", Json::from(finding.message.to_string())),
+                    (
+                        "first_seen",
+                        Json::from(finding.first_seen.map(|time| timestamps.iso(time))),
+                    ),
+                ])
+            })
+            .collect();
+        let fixes: Vec<Json> = report
+            .fixes
+            .iter()
+            .map(|fix| {
+                Json::object([
+                    ("action", Json::from(fix.action.as_str())),
+                    ("outcome", Json::from(fix.outcome.as_str())),
+                    ("error", Json::from(fix.outcome.error())),
+                    ("message", Json::from(fix.message.to_string())),
+                ])
+            })
+            .collect();
+        let level = report.findings.iter().map(|finding| finding.level).max();
+        let payload = Json::object([
+            ("path", Json::from(report.path.display().to_string())),
+            ("clean", Json::from(report.findings.is_empty())),
+            ("level", Json::from(level.map(|l| l.as_str()))),
+            ("stage", Json::from(report.stage.map(|s| s.as_str()))),
+            ("findings", Json::Array(findings)),
+            ("fixes", Json::Array(fixes)),
+        ]);
+        messages.push((
+            format!("{topic}/repo/{}", report.name.replace(['/', '#', '+'], "_")),
+            payload.to_string(),
+        ));
+    }
+    messages
+}
+
+// Split a broker into its host and port: `host`, `host:port`, or an IPv6 address on its own or as `[address]:port`.
+fn host_port(broker: &str) -> anyhow::Result<(&str, u16)> {
+    let port = |port: &str| {
+        port.parse()
+            .with_context(|| format!("invalid MQTT broker port in \"{broker}\""))
+    };
+    if let Some(bracketed) = broker.strip_prefix('[') {
+        return match bracketed.split_once(']') {
+            Some((host, "")) => Ok((host, DEFAULT_PORT)),
+            Some((host, rest)) => match rest.strip_prefix(':') {
+                Some(rest) => Ok((host, port(rest)?)),
+                None => anyhow::bail!("invalid MQTT broker \"{broker}\""),
+            },
+            None => anyhow::bail!("invalid MQTT broker \"{broker}\", missing `]`"),
+        };
+    }
+    match broker.split_once(':') {
+        // More than one colon is an IPv6 address without a port
+        Some((_, rest)) if rest.contains(':') => Ok((broker, DEFAULT_PORT)),
+        Some((host, rest)) => Ok((host, port(rest)?)),
+        None => Ok((broker, DEFAULT_PORT)),
+    }
+}
+
+/// Publish the scan results to an MQTT broker given as `host`, `host:port` or `[ipv6]:port`. Messages are retained
+/// so that subscribers see the latest results even if they connect after the scan, and are published with QoS 1, so
+/// this only disconnects once the broker has acknowledged every one.
+pub fn publish(
+    broker: &str,
+    topic: &str,
+    reports: &[RepoReport],
+    timestamps: &Timestamps,
+) -> anyhow::Result<()> {
+    let (host, port) = host_port(broker)?;
+
+    let mut options = MqttOptions::new(concat!("kamino-", env!("CARGO_PKG_VERSION")), host, port);
+    options.set_keep_alive(Duration::from_secs(10));
+    let messages = messages(topic, reports, timestamps);
+    let (mut client, mut connection) = Client::new(options, messages.len() + 1);
+
+    let mut unacknowledged = messages.len();
+    for (topic, payload) in messages {
+        client
+            .publish(topic, QoS::AtLeastOnce, true, payload)
+            .context("failed to queue MQTT message")?;
+    }
+    if unacknowledged == 0 {
+        client
+            .disconnect()
+            .context("failed to queue MQTT disconnect")?;
+    }
+
+    // Drive the connection until the broker has acknowledged everything and the disconnect has been sent
+    for notification in connection.iter() {
+        match notification {
+            Ok(Event::Incoming(Packet::PubAck(_))) => {
+                unacknowledged = unacknowledged.saturating_sub(1);
+                if unacknowledged == 0 {
+                    client
+                        .disconnect()
+                        .context("failed to queue MQTT disconnect")?;
+                }
+            }
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+            Ok(_) => (),
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to publish to MQTT broker {broker}"))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        findings::{Finding, Fix, RemediationResult},
+        zone::TimeZone,
+    };
+
+    #[test]
+    fn broker_host_port() {
+        assert_eq!(host_port("broker").unwrap(), ("broker", 1883));
+        assert_eq!(host_port("broker:8883").unwrap(), ("broker", 8883));
+        assert_eq!(
+            host_port("192.168.1.2:1884").unwrap(),
+            ("192.168.1.2", 1884)
+        );
+        assert_eq!(host_port("[::1]:8883").unwrap(), ("::1", 8883));
+        assert_eq!(host_port("[fe80::1]").unwrap(), ("fe80::1", 1883));
+        assert_eq!(host_port("fe80::1").unwrap(), ("fe80::1", 1883));
+        assert!(host_port("broker:port").is_err());
+        assert!(host_port("[::1]8883").is_err());
+        assert!(host_port("[::1:8883").is_err());
+    }
+
+    #[test]
+    fn payloads() {
+        let reports = vec![
+            RepoReport::for_test("clean", Vec::new()),
+            RepoReport {
+                fixes: vec![RemediationResult::failed(
+                    Fix::Pull,
+                    "Fast-forward branch dev",
+                    "not possible",
+                )],
+                ..RepoReport::for_test(
+                    "dirty",
+                    vec![
+                        Finding {
+                            first_seen: Some(1_709_301_900),
+                            ..Finding::new(Check::Ahead, "Branch main is ahead")
+                        },
+                        Finding::new(Check::Behind, "Branch dev is behind"),
+                    ],
+                )
+            },
+        ];
+
+        let timestamps = Timestamps::new(TimeZone::default(), 1_709_301_900);
+        let messages = messages("home/kamino", &reports, &timestamps);
+        assert_eq!(
+            messages[0],
+            (
+                "home/kamino/summary".into(),
+                r#"{"repos":2,"repos_with_findings":1,"findings":2,"uncommitted":0,"unpushed":1,"behind":1}"#
+                    .into()
+            )
+        );
+        assert_eq!(
+            messages[1],
+            (
+                "home/kamino/repo/clean".into(),
+                r#"{"path":"/repos/clean","clean":true,"level":null,"stage":null,"findings":[],"fixes":[]}"#
+                    .into()
+            )
+        );
+        assert_eq!(messages[2].0, "home/kamino/repo/dirty");
+        assert!(messages[2].1.contains(r#""level":"error""#));
+        assert!(messages[2]
+            .1
+            .contains(r#""first_seen":"2024-03-01T14:05:00Z""#));
+        assert!(messages[2].1.contains(r#""first_seen":null"#));
+        assert!(messages[2].1.contains(
+            r#""fixes":[{"action":"pull","outcome":"failed","error":"not possible","message":"Fast-forward branch dev"}]"#
+        ));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::render::Renderer;
+use std::{
+    cell::Cell,
+    io::{self, IsTerminal, Write},
+};
+
+/// Writes the human-readable report to stdout, and errors to stderr.
+///
+/// Every line printed goes through the [`Renderer`], so terminal formatting rules apply everywhere.
+///
+/// Lines are either status (banners and progress) or report (findings). When `quiet_when_clean` is set, status
+/// lines are held back until the first report line, so a run with nothing to report prints nothing at all. When
+/// `quiet` is set, status lines are never printed.
+///
+/// Progress, e.g. of a fetch, is shown on a line of stderr which is overwritten each time, but only when stderr is a
+/// terminal, so it never ends up in logs.
+pub struct Output {
+    renderer: Renderer,
+    quiet: bool,
+    quiet_when_clean: bool,
+    pending: Vec<String>,
+    reported: bool,
+    /// Lines kept instead of printed, for writing the text to a file.
+    captured: Option<Vec<String>>,
+    /// Whether a progress line is showing, to be cleared before anything else is printed.
+    progress_shown: Cell<bool>,
+}
+
+impl Output {
+    pub fn new(renderer: Renderer) -> Self {
+        Self {
+            renderer,
+            quiet: false,
+            quiet_when_clean: false,
+            pending: Vec::new(),
+            reported: false,
+            captured: None,
+            progress_shown: Cell::new(false),
+        }
+    }
+
+    /// Keep the lines that would be printed to stdout, to get them back with [`Output::into_captured()`].
+    pub fn capture(renderer: Renderer) -> Self {
+        Self {
+            captured: Some(Vec::new()),
+            ..Self::new(renderer)
+        }
+    }
+
+    /// The lines kept by an output from [`Output::capture()`], each ending with a newline.
+    pub fn into_captured(self) -> String {
+        self.captured
+            .unwrap_or_default()
+            .into_iter()
+            .map(|line| line + "\n")
+            .collect()
+    }
+
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+
+    pub fn set_quiet_when_clean(&mut self, quiet_when_clean: bool) {
+        self.quiet_when_clean = quiet_when_clean;
+    }
+
+    pub fn renderer(&self) -> &Renderer {
+        &self.renderer
+    }
+
+    /// Print a line that is only worth seeing alongside findings.
+    pub fn status(&mut self, line: impl AsRef<str>) {
+        tracing::info!("{}", line.as_ref());
+        if self.quiet {
+            return;
+        }
+        let line = self.renderer.line(line.as_ref());
+        if self.quiet_when_clean && !self.reported {
+            self.pending.push(line);
+        } else {
+            self.print(line);
+        }
+    }
+
+    /// Print a line that is worth reporting on its own.
+    pub fn report(&mut self, line: impl AsRef<str>) {
+        self.reported = true;
+        for pending in std::mem::take(&mut self.pending) {
+            self.print(pending);
+        }
+        let line = self.renderer.line(line.as_ref());
+        self.print(line);
+    }
+
+    fn print(&mut self, line: String) {
+        self.clear_progress();
+        match &mut self.captured {
+            Some(captured) => captured.push(line),
+            None => println!("{line}"),
+        }
+    }
+
+    /// Print a warning or error to stderr. These are never held back.
+    pub fn error(&self, line: impl AsRef<str>) {
+        tracing::warn!("{}", line.as_ref());
+        self.clear_progress();
+        eprintln!("{}", self.renderer.line(line.as_ref()));
+    }
+
+    /// Show how something slow is going, in place of the last progress shown.
+    pub fn progress(&self, line: impl AsRef<str>) {
+        if self.quiet || self.captured.is_some() || !io::stderr().is_terminal() {
+            return;
+        }
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r{}\x1b[K", self.renderer.line(line.as_ref()));
+        let _ = stderr.flush();
+        self.progress_shown.set(true);
+    }
+
+    /// Remove the progress line, if one is showing.
+    pub fn clear_progress(&self) {
+        if self.progress_shown.replace(false) {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r\x1b[K");
+            let _ = stderr.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render::Timestamps;
+
+    fn capturing() -> Output {
+        Output::capture(Renderer::with_timestamps(Timestamps::default()))
+    }
+
+    #[test]
+    fn status_and_report() {
+        let mut output = capturing();
+        output.status("Checking /repos");
+        output.report("my_repo: Has uncommitted changes");
+        output.status("Summary: 1 repos scanned");
+        assert_eq!(
+            output.into_captured(),
+            "Checking /repos\nmy_repo: Has uncommitted changes\nSummary: 1 repos scanned\n"
+        );
+    }
+
+    #[test]
+    fn quiet_when_clean() {
+        // A clean run prints nothing
+        let mut output = capturing();
+        output.set_quiet_when_clean(true);
+        output.status("Checking /repos");
+        output.status("Summary: 1 repos scanned");
+        assert_eq!(output.into_captured(), "");
+
+        // A run with findings prints the status lines held back first, then the rest as it goes
+        let mut output = capturing();
+        output.set_quiet_when_clean(true);
+        output.status("Checking /repos");
+        output.status("clean: OK");
+        output.report("my_repo: Has uncommitted changes");
+        output.status("Summary: 2 repos scanned");
+        assert_eq!(
+            output.into_captured(),
+            "Checking /repos\nclean: OK\nmy_repo: Has uncommitted changes\nSummary: 2 repos scanned\n"
+        );
+    }
+
+    #[test]
+    fn quiet() {
+        // Only findings are printed, even once there are some
+        let mut output = capturing();
+        output.set_quiet(true);
+        output.status("Checking /repos");
+        output.report("my_repo: Has uncommitted changes");
+        output.status("Summary: 1 repos scanned");
+        assert_eq!(output.into_captured(), "my_repo: Has uncommitted changes\n");
+    }
+}
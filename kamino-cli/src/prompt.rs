@@ -0,0 +1,170 @@
+use kamino_core::CredentialPrompt;
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+// Whether a question turned the terminal's echo off and hasn't turned it back on yet.
+static ECHO_OFF: AtomicBool = AtomicBool::new(false);
+
+/// Asks for credentials on the terminal, for `--ask-credentials`: questions go to stderr and answers are read from
+/// stdin, with echo turned off for passwords and passphrases where `stty` can do that.
+///
+/// Fetches may ask from their own threads, so only one question is asked at a time. A fetch isn't given up on while
+/// it is asking, but in case kamino exits while a question is still waiting, [`restore_echo()`] turns echo back on.
+pub struct TerminalPrompt {
+    store: bool,
+    lock: Mutex<()>,
+}
+
+impl TerminalPrompt {
+    /// A prompt which also stores answers that worked with git's credential helper if `store` is set.
+    pub fn new(store: bool) -> Self {
+        Self {
+            store,
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+impl CredentialPrompt for TerminalPrompt {
+    fn user_pass(&self, url: &str, username: Option<&str>) -> Option<(String, String)> {
+        let _lock = self.lock.lock().ok()?;
+        let username = match username {
+            Some(username) => username.to_owned(),
+            None => ask(&format!("Username for {url}: "), false).filter(|u| !u.is_empty())?,
+        };
+        let password = ask(&format!("Password for {username} at {url}: "), true)?;
+        Some((username, password))
+    }
+
+    fn passphrase(&self, key: &Path) -> Option<String> {
+        let _lock = self.lock.lock().ok()?;
+        ask(&format!("Passphrase for {}: ", key.display()), true)
+    }
+
+    fn approved(&self, url: &str, username: &str, password: &str) {
+        if !self.store {
+            return;
+        }
+        let _lock = self.lock.lock();
+        if let Err(e) = store(url, username, password) {
+            eprintln!("\r\x1b[KCouldn't store the credentials for {url}: {e}");
+        }
+    }
+}
+
+// Ask a question and read a line, without echoing it if `secret`. Returns None at the end of input.
+fn ask(question: &str, secret: bool) -> Option<String> {
+    // Starting with a clear line, since a fetch's progress may be drawn on it
+    eprint!("\r\x1b[K{question}");
+    io::stderr().flush().ok()?;
+    let hidden = secret.then(Hidden::new).flatten();
+    let mut answer = String::new();
+    let read = io::stdin().lock().read_line(&mut answer);
+    if hidden.is_some() {
+        drop(hidden);
+        eprintln!();
+    }
+    match read {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(answer.trim_end_matches(['\r', '\n']).to_owned()),
+    }
+}
+
+/// Turn the terminal's echo back on if a question left it off, e.g. one still waiting for an answer when kamino exits.
+pub fn restore_echo() {
+    if ECHO_OFF.swap(false, Ordering::SeqCst) {
+        set_echo(true);
+    }
+}
+
+// The terminal's echo turned off for a secret answer, until dropped.
+struct Hidden;
+
+impl Hidden {
+    fn new() -> Option<Self> {
+        set_echo(false).then(|| {
+            ECHO_OFF.store(true, Ordering::SeqCst);
+            Self
+        })
+    }
+}
+
+impl Drop for Hidden {
+    fn drop(&mut self) {
+        restore_echo();
+    }
+}
+
+// Turn the terminal's echo on or off, saying whether it worked.
+#[cfg(unix)]
+fn set_echo(on: bool) -> bool {
+    Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(Stdio::inherit())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(not(unix))]
+fn set_echo(_on: bool) -> bool {
+    false
+}
+
+// Hand credentials which worked to git's credential helper, as `git credential approve` does.
+fn store(url: &str, username: &str, password: &str) -> io::Result<()> {
+    let input = credential_input(url, username, password)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "they contain a line break"))?;
+    let mut child = Command::new("git")
+        .args(["credential", "approve"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(input.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("git credential approve {status}")));
+    }
+    Ok(())
+}
+
+// The input to `git credential`, which is line based, so values can't contain line breaks.
+fn credential_input(url: &str, username: &str, password: &str) -> Option<String> {
+    let values = [url, username, password];
+    if values
+        .iter()
+        .any(|value| value.contains(['\n', '\r', '\0']))
+    {
+        return None;
+    }
+    Some(format!(
+        "url={url}\nusername={username}\npassword={password}\n\n"
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn credential_input_lines() {
+        assert_eq!(
+            credential_input("https://example.com/repo.git", "me", "hunter2").as_deref(),
+            Some("url=https://example.com/repo.git\nusername=me\npassword=hunter2\n\n")
+        );
+        assert_eq!(
+            credential_input("https://example.com/repo.git", "me", "two\nlines"),
+            None
+        );
+    }
+}
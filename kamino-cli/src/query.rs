@@ -0,0 +1,560 @@
+use crate::findings::{Check, Level, RepoReport};
+use std::{fmt, str::FromStr};
+
+/// An expression which picks out repos by what a scan found in them, for `kamino list --where`.
+///
+/// ```text
+/// behind > 10 && !dirty
+/// has(hooks) || (stage == "stale" && stashes)
+/// ```
+///
+/// Conditions are combined with `&&`, `||`, `!` and parentheses. A condition is a comparison with `==`, `!=`, `<`,
+/// `<=`, `>` or `>=`, a field on its own (true if it is non-zero), or `has(<check or level>)`, which is true if the repo
+/// has any finding from that check or at that level or above. Fields are counts (`ahead`, `behind`, `stashes`,
+/// `hook_problems`, `at_risk` in bytes and `findings`), flags (`dirty` and `clean`) and text (`name`, `path` and
+/// `stage`, which is empty if the repo has no activity at all).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Query {
+    text: String,
+    expr: Expr,
+}
+
+/// Error returned when a query is malformed, with the column it was found at, counting from 1.
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+#[error("{message} at column {column}")]
+pub struct QueryError {
+    message: String,
+    column: usize,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, Op, Operand),
+    Truthy(Operand),
+    HasCheck(Check),
+    HasLevel(Level),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Operand {
+    Field(Field),
+    Number(u64),
+    Text(String),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Field {
+    Ahead,
+    Behind,
+    Stashes,
+    HookProblems,
+    AtRisk,
+    Findings,
+    Dirty,
+    Clean,
+    Name,
+    Path,
+    Stage,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Type {
+    Number,
+    Flag,
+    Text,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
+enum Value {
+    Number(u64),
+    Flag(bool),
+    Text(String),
+}
+
+impl Field {
+    const ALL: [(&'static str, Self); 11] = [
+        ("ahead", Self::Ahead),
+        ("behind", Self::Behind),
+        ("stashes", Self::Stashes),
+        ("hook_problems", Self::HookProblems),
+        ("at_risk", Self::AtRisk),
+        ("findings", Self::Findings),
+        ("dirty", Self::Dirty),
+        ("clean", Self::Clean),
+        ("name", Self::Name),
+        ("path", Self::Path),
+        ("stage", Self::Stage),
+    ];
+
+    fn ty(self) -> Type {
+        match self {
+            Self::Ahead
+            | Self::Behind
+            | Self::Stashes
+            | Self::HookProblems
+            | Self::AtRisk
+            | Self::Findings => Type::Number,
+            Self::Dirty | Self::Clean => Type::Flag,
+            Self::Name | Self::Path | Self::Stage => Type::Text,
+        }
+    }
+
+    fn value(self, report: &RepoReport) -> Value {
+        let summary = &report.summary;
+        match self {
+            Self::Ahead => Value::Number(summary.ahead as u64),
+            Self::Behind => Value::Number(summary.behind as u64),
+            Self::Stashes => Value::Number(summary.stashes as u64),
+            Self::HookProblems => Value::Number(summary.hook_problems as u64),
+            Self::AtRisk => Value::Number(summary.at_risk),
+            Self::Findings => Value::Number(report.findings.len() as u64),
+            Self::Dirty => Value::Flag(summary.uncommitted > 0),
+            Self::Clean => Value::Flag(report.findings.is_empty()),
+            Self::Name => Value::Text(report.name.clone()),
+            Self::Path => Value::Text(report.path.to_string_lossy().into_owned()),
+            Self::Stage => Value::Text(report.stage.map_or("", |s| s.as_str()).to_owned()),
+        }
+    }
+}
+
+impl Operand {
+    fn ty(&self) -> Type {
+        match self {
+            Self::Field(field) => field.ty(),
+            Self::Number(_) => Type::Number,
+            Self::Text(_) => Type::Text,
+        }
+    }
+
+    fn value(&self, report: &RepoReport) -> Value {
+        match self {
+            Self::Field(field) => field.value(report),
+            Self::Number(n) => Value::Number(*n),
+            Self::Text(text) => Value::Text(text.clone()),
+        }
+    }
+}
+
+impl Query {
+    /// Whether a repo's report matches the query.
+    pub fn matches(&self, report: &RepoReport) -> bool {
+        self.expr.eval(report)
+    }
+}
+
+impl Expr {
+    fn eval(&self, report: &RepoReport) -> bool {
+        match self {
+            Self::Or(a, b) => a.eval(report) || b.eval(report),
+            Self::And(a, b) => a.eval(report) && b.eval(report),
+            Self::Not(a) => !a.eval(report),
+            Self::Compare(a, op, b) => {
+                let (a, b) = (a.value(report), b.value(report));
+                match op {
+                    Op::Eq => a == b,
+                    Op::Ne => a != b,
+                    Op::Lt => a < b,
+                    Op::Le => a <= b,
+                    Op::Gt => a > b,
+                    Op::Ge => a >= b,
+                }
+            }
+            Self::Truthy(operand) => match operand.value(report) {
+                Value::Number(n) => n > 0,
+                Value::Flag(b) => b,
+                Value::Text(text) => !text.is_empty(),
+            },
+            Self::HasCheck(check) => report.findings.iter().any(|f| f.check == *check),
+            Self::HasLevel(level) => report.findings.iter().any(|f| f.level >= *level),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Text(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+// Split a query into tokens, each with the column it starts at.
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().enumerate().peekable();
+    while let Some((i, c)) = chars.next() {
+        let column = i + 1;
+        let mut next_is = |expected: char| chars.next_if(|&(_, c)| c == expected).is_some();
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' if next_is('&') => Token::And,
+            '|' if next_is('|') => Token::Or,
+            '=' if next_is('=') => Token::Op(Op::Eq),
+            '!' if next_is('=') => Token::Op(Op::Ne),
+            '!' => Token::Not,
+            '<' if next_is('=') => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '>' if next_is('=') => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            '"' | '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, end)) if end == c => break,
+                        Some((_, other)) => value.push(other),
+                        None => {
+                            return Err(QueryError {
+                                message: "unclosed string".into(),
+                                column,
+                            })
+                        }
+                    }
+                }
+                Token::Text(value)
+            }
+            c if c.is_ascii_digit() => {
+                let mut digits = String::from(c);
+                while let Some((_, digit)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                    digits.push(digit);
+                }
+                let number = digits.parse().map_err(|_| QueryError {
+                    message: format!("number {digits} is too big"),
+                    column,
+                })?;
+                Token::Number(number)
+            }
+            // Names may have dashes in, like check names
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = String::from(c);
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+                {
+                    name.push(c);
+                }
+                Token::Ident(name)
+            }
+            other => {
+                return Err(QueryError {
+                    message: format!("unexpected \"{other}\""),
+                    column,
+                })
+            }
+        };
+        tokens.push((token, column));
+    }
+    Ok(tokens)
+}
+
+// Recursive descent over the tokens, lowest precedence first: `||`, `&&`, `!`, then comparisons.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |(_, column)| *column)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, QueryError> {
+        Err(QueryError {
+            message: message.into(),
+            column: self.column(),
+        })
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(token, _)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<(), QueryError> {
+        if self.peek() == Some(&expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            self.error(format!("expected {what}"))
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, QueryError> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.condition()
+    }
+
+    fn condition(&mut self) -> Result<Expr, QueryError> {
+        match self.peek() {
+            Some(Token::Open) => {
+                self.pos += 1;
+                let expr = self.or()?;
+                self.expect(Token::Close, "\")\"")?;
+                return Ok(expr);
+            }
+            Some(Token::Ident(name)) if name == "has" => {
+                self.pos += 1;
+                return self.has();
+            }
+            _ => {}
+        }
+
+        let column = self.column();
+        let left = self.operand()?;
+        let op = match self.peek() {
+            Some(Token::Op(op)) => *op,
+            _ => {
+                if left.ty() == Type::Text {
+                    return Err(QueryError {
+                        message: "text must be compared with something".into(),
+                        column,
+                    });
+                }
+                return Ok(Expr::Truthy(left));
+            }
+        };
+        self.pos += 1;
+        let right = self.operand()?;
+        if left.ty() != right.ty() {
+            return Err(QueryError {
+                message: format!("can't compare {} with {}", left.ty(), right.ty()),
+                column,
+            });
+        }
+        if left.ty() != Type::Number && !matches!(op, Op::Eq | Op::Ne) {
+            return Err(QueryError {
+                message: format!("{} can only be compared with == or !=", left.ty()),
+                column,
+            });
+        }
+        Ok(Expr::Compare(left, op, right))
+    }
+
+    fn has(&mut self) -> Result<Expr, QueryError> {
+        self.expect(Token::Open, "\"(\" after has")?;
+        let column = self.column();
+        let name = match self.next() {
+            Some(Token::Ident(name)) => name,
+            _ => {
+                self.pos -= 1;
+                return self.error("expected a check or level");
+            }
+        };
+        // Check names can be written with underscores, like the fields
+        let dashed = name.replace('_', "-");
+        let expr = if let Some(check) = Check::ALL.into_iter().find(|c| c.as_str() == dashed) {
+            Expr::HasCheck(check)
+        } else if let Some(level) = Level::ALL.into_iter().find(|l| l.as_str() == name) {
+            Expr::HasLevel(level)
+        } else {
+            return Err(QueryError {
+                message: format!("unknown check or level \"{name}\""),
+                column,
+            });
+        };
+        self.expect(Token::Close, "\")\"")?;
+        Ok(expr)
+    }
+
+    fn operand(&mut self) -> Result<Operand, QueryError> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Operand::Number(n)),
+            Some(Token::Text(text)) => Ok(Operand::Text(text)),
+            Some(Token::Ident(name)) => match Field::ALL.iter().find(|(n, _)| *n == name) {
+                Some((_, field)) => Ok(Operand::Field(*field)),
+                None => {
+                    self.pos -= 1;
+                    let fields: Vec<&str> = Field::ALL.iter().map(|(name, _)| *name).collect();
+                    self.error(format!(
+                        "unknown field \"{name}\" (expected one of {})",
+                        fields.join(", ")
+                    ))
+                }
+            },
+            _ => {
+                self.pos -= 1;
+                self.error("expected a field, number or text")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Number => "a number",
+            Self::Flag => "a flag",
+            Self::Text => "text",
+        })
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+            end: s.chars().count() + 1,
+        };
+        let expr = parser.or()?;
+        if parser.pos < parser.tokens.len() {
+            return parser.error("expected \"&&\", \"||\" or the end");
+        }
+        Ok(Self {
+            text: s.to_owned(),
+            expr,
+        })
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{findings::Finding, lifecycle::Stage};
+    use kamino_core::Summary;
+
+    // Every repo is stale, so that queries on the stage can be checked.
+    fn report(name: &str, findings: Vec<Finding>, summary: Summary) -> RepoReport {
+        RepoReport {
+            stage: Some(Stage::Stale),
+            summary,
+            ..RepoReport::for_test(name, findings)
+        }
+    }
+
+    #[test]
+    fn matches() {
+        let behind = report(
+            "behind",
+            vec![Finding::new(Check::Behind, "behind")],
+            Summary {
+                behind: 12,
+                ..Summary::default()
+            },
+        );
+        let dirty = report(
+            "dirty",
+            vec![
+                Finding::new(Check::Uncommitted, "dirty"),
+                Finding::new(Check::Hooks, "hook"),
+            ],
+            Summary {
+                behind: 12,
+                uncommitted: 1,
+                hook_problems: 1,
+                ..Summary::default()
+            },
+        );
+        let clean = report("clean", Vec::new(), Summary::default());
+
+        let matching = |query: &str| -> Vec<String> {
+            let query: Query = query.parse().unwrap();
+            [&behind, &dirty, &clean]
+                .into_iter()
+                .filter(|report| query.matches(report))
+                .map(|report| report.name.clone())
+                .collect()
+        };
+        assert_eq!(matching("behind > 10 && !dirty"), ["behind"]);
+        assert_eq!(matching("has(hooks)"), ["dirty"]);
+        assert_eq!(matching("has(error) || clean"), ["dirty", "clean"]);
+        assert_eq!(matching("has(warning)"), ["dirty"]);
+        assert_eq!(matching("hook_problems"), ["dirty"]);
+        assert_eq!(matching("!(behind >= 12)"), ["clean"]);
+        assert_eq!(
+            matching("name == 'clean' || findings == 2"),
+            ["dirty", "clean"]
+        );
+        assert_eq!(matching("stage != \"stale\""), Vec::<String>::new());
+        assert_eq!(matching("dirty == clean"), ["behind"]);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |query: &str| query.parse::<Query>().unwrap_err().to_string();
+        assert_eq!(
+            error("behind >"),
+            "expected a field, number or text at column 9"
+        );
+        assert_eq!(
+            error("has(hook_mismatch)"),
+            "unknown check or level \"hook_mismatch\" at column 5"
+        );
+        assert_eq!(
+            error("name"),
+            "text must be compared with something at column 1"
+        );
+        assert_eq!(
+            error("name > 'a'"),
+            "text can only be compared with == or != at column 1"
+        );
+        assert_eq!(
+            error("dirty == 1"),
+            "can't compare a flag with a number at column 1"
+        );
+        assert_eq!(error("(dirty"), "expected \")\" at column 7");
+        assert_eq!(
+            error("dirty clean"),
+            "expected \"&&\", \"||\" or the end at column 7"
+        );
+        assert_eq!(error("name == 'x"), "unclosed string at column 9");
+        assert_eq!(error("dirty & clean"), "unexpected \"&\" at column 7");
+        assert!(error("behnd > 1")
+            .starts_with("unknown field \"behnd\" (expected one of ahead, behind,"));
+    }
+}
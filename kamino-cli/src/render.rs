@@ -0,0 +1,512 @@
+use crate::{
+    changed::{civil_from_days, DAY},
+    findings::{Check, Finding, Level, Message, Segment},
+    zone::TimeZone,
+};
+use kamino_core::{Clock, SystemClock};
+use std::{
+    env,
+    io::{self, IsTerminal},
+    path::Path,
+};
+
+/// When to render terminal hyperlinks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum Hyperlinks {
+    /// Only if stdout is a terminal that is known to support them.
+    Auto,
+    Always,
+    Never,
+}
+
+/// When to color output.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum Color {
+    /// Only if stdout is a terminal, and `NO_COLOR` isn't set.
+    Auto,
+    Always,
+    Never,
+}
+
+/// Colors used for findings, by how urgent they are.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Paint {
+    Red,
+    Yellow,
+    Green,
+}
+
+impl Paint {
+    fn code(self) -> &'static str {
+        match self {
+            Self::Red => "31",
+            Self::Yellow => "33",
+            Self::Green => "32",
+        }
+    }
+}
+
+/// Command line options for terminal output.
+#[derive(clap::Args)]
+pub struct RenderArgs {
+    /// Render repo paths and branches as clickable terminal hyperlinks
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        value_name = "WHEN",
+        default_value = "auto"
+    )]
+    pub hyperlinks: Hyperlinks,
+
+    /// Color findings by how urgent they are
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        value_name = "WHEN",
+        default_value = "auto"
+    )]
+    pub color: Color,
+
+    /// Only print plain ASCII: no Unicode, colors or terminal control sequences
+    #[clap(long, global = true)]
+    pub ascii: bool,
+
+    /// Show times in UTC
+    #[clap(long, global = true, conflicts_with = "local")]
+    pub utc: bool,
+
+    /// Show times in the local time zone, from TZ or /etc/localtime (the default)
+    #[clap(long, global = true)]
+    pub local: bool,
+}
+
+/// How times are shown: as an ISO 8601 date and time in a time zone, then how long ago that was.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Timestamps {
+    zone: TimeZone,
+    /// What times are relative to, in seconds since the Unix epoch.
+    now: i64,
+}
+
+impl Timestamps {
+    pub fn new(zone: TimeZone, now: i64) -> Self {
+        Self { zone, now }
+    }
+
+    /// Render a time as ISO 8601 in the time zone, e.g. "2024-03-01T14:05:00+01:00". UTC is shown as "Z".
+    pub fn iso(&self, time: i64) -> String {
+        let offset = self.zone.offset(time);
+        let local = time + i64::from(offset);
+        let (year, month, day) = civil_from_days(local.div_euclid(DAY));
+        let seconds = local.rem_euclid(DAY);
+        let zone = match offset {
+            0 => "Z".to_owned(),
+            _ => format!(
+                "{}{:02}:{:02}",
+                if offset < 0 { '-' } else { '+' },
+                offset.abs() / 3600,
+                offset.abs() % 3600 / 60
+            ),
+        };
+        format!(
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}{zone}",
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        )
+    }
+
+    /// Describe how long ago a time was, e.g. "3 weeks ago", or how far in the future it is.
+    pub fn relative(&self, time: i64) -> String {
+        const MINUTE: i64 = 60;
+        const HOUR: i64 = 60 * MINUTE;
+        let elapsed = self.now - time;
+        let (count, unit) = match elapsed.abs() {
+            seconds if seconds < MINUTE => return "just now".to_owned(),
+            seconds if seconds < HOUR => (seconds / MINUTE, "minute"),
+            seconds if seconds < DAY => (seconds / HOUR, "hour"),
+            seconds if seconds < 14 * DAY => (seconds / DAY, "day"),
+            seconds if seconds < 60 * DAY => (seconds / (7 * DAY), "week"),
+            seconds if seconds < 365 * DAY => (seconds / (30 * DAY), "month"),
+            seconds => (seconds / (365 * DAY), "year"),
+        };
+        let plural = if count == 1 { "" } else { "s" };
+        if elapsed < 0 {
+            format!("in {count} {unit}{plural}")
+        } else {
+            format!("{count} {unit}{plural} ago")
+        }
+    }
+
+    /// Render a time both ways, e.g. "2024-03-01T14:05:00Z (3 weeks ago)".
+    pub fn format(&self, time: i64) -> String {
+        format!("{} ({})", self.iso(time), self.relative(time))
+    }
+}
+
+/// Turns report content into text for the terminal.
+///
+/// All terminal-specific formatting goes through here so that it can be turned on and off in one place.
+#[derive(Clone, Debug)]
+pub struct Renderer {
+    hyperlinks: bool,
+    color: bool,
+    ascii: bool,
+    timestamps: Timestamps,
+}
+
+impl Renderer {
+    pub fn new(args: &RenderArgs) -> Self {
+        let hyperlinks = match args.hyperlinks {
+            _ if args.ascii => false,
+            Hyperlinks::Always => true,
+            Hyperlinks::Never => false,
+            Hyperlinks::Auto => io::stdout().is_terminal() && terminal_supports_hyperlinks(),
+        };
+        let color = match args.color {
+            _ if args.ascii => false,
+            Color::Always => true,
+            Color::Never => false,
+            Color::Auto => {
+                io::stdout().is_terminal()
+                    && env::var_os("NO_COLOR").is_none()
+                    && env::var("TERM").map_or(true, |term| term != "dumb")
+            }
+        };
+        let zone = if args.utc {
+            TimeZone::default()
+        } else {
+            TimeZone::local()
+        };
+        Self {
+            hyperlinks,
+            color,
+            ascii: args.ascii,
+            timestamps: Timestamps::new(zone, SystemClock.now()),
+        }
+    }
+
+    /// Render plain text, with no colors or hyperlinks, e.g. for writing to a file. Times are shown the same way.
+    pub fn plain(&self) -> Self {
+        Self::with_timestamps(self.timestamps.clone())
+    }
+
+    /// Render plain text, showing times as given.
+    pub fn with_timestamps(timestamps: Timestamps) -> Self {
+        Self {
+            hyperlinks: false,
+            color: false,
+            ascii: false,
+            timestamps,
+        }
+    }
+
+    /// How times are shown.
+    pub fn timestamps(&self) -> &Timestamps {
+        &self.timestamps
+    }
+
+    /// Finish a line of output before it is printed. In ASCII mode, anything that isn't printable ASCII
+    /// (including text that came from outside, like file names) is escaped.
+    pub fn line(&self, line: &str) -> String {
+        if self.ascii {
+            line.chars()
+                .map(|c| {
+                    if c.is_ascii() && !c.is_ascii_control() {
+                        c.to_string()
+                    } else {
+                        c.escape_unicode().to_string()
+                    }
+                })
+                .collect()
+        } else {
+            line.to_owned()
+        }
+    }
+
+    /// Render the path to a repo, linking to it as a `file://` URL.
+    pub fn repo_path(&self, path: &Path) -> String {
+        let text = path.display().to_string();
+        match path.canonicalize() {
+            Ok(absolute) => self.link(&text, &file_url(&absolute)),
+            Err(_) => text,
+        }
+    }
+
+    /// Render a finding's message, linking any references which have a URL.
+    pub fn message(&self, message: &Message) -> String {
+        message
+            .0
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.clone(),
+                Segment::Ref {
+                    text,
+                    url: Some(url),
+                } => self.link(text, url),
+                Segment::Ref { text, url: None } => text.clone(),
+                Segment::Time(time) => self.timestamps.format(*time),
+            })
+            .collect()
+    }
+
+    /// Render a finding's message, colored by how urgent it is: red for changes which aren't in sync with the
+    /// remote, yellow for things that probably need attention, and plain for information. A finding which was
+    /// escalated for being around a while is colored by its level instead, and says when it was first seen.
+    pub fn finding(&self, finding: &Finding) -> String {
+        let paint = match finding.check {
+            _ if finding.is_escalated() => match finding.level {
+                Level::Error => Some(Paint::Red),
+                Level::Warning | Level::Info => Some(Paint::Yellow),
+            },
+            Check::Uncommitted
+            | Check::State
+            | Check::Remote
+            | Check::Ahead
+            | Check::Behind
+            | Check::Broken => Some(Paint::Red),
+            Check::Base | Check::CheckedOut | Check::Merged | Check::Stale | Check::PerfConfig => {
+                None
+            }
+            Check::Stashed
+            | Check::StaleStash
+            | Check::RemoteUrl
+            | Check::Fetch
+            | Check::FetchAge
+            | Check::RemoteHead
+            | Check::Shallow
+            | Check::Tags
+            | Check::Grafts
+            | Check::Lfs
+            | Check::LargeFiles
+            | Check::TrackedIgnored
+            | Check::Submodules
+            | Check::Nested
+            | Check::Worktrees
+            | Check::Hooks
+            | Check::Permissions
+            | Check::Identity
+            | Check::Signatures => Some(Paint::Yellow),
+        };
+        let text = self.message(&finding.describe());
+        match paint {
+            Some(paint) => self.paint(paint, &text),
+            None => text,
+        }
+    }
+
+    /// Render a line of a finding's detail. Lines of a diff are colored, red for removed and green for added.
+    pub fn detail(&self, line: &str) -> String {
+        if line.starts_with("---") || line.starts_with("+++") {
+            line.to_owned()
+        } else if line.starts_with('-') {
+            self.paint(Paint::Red, line)
+        } else if line.starts_with('+') {
+            self.paint(Paint::Green, line)
+        } else {
+            line.to_owned()
+        }
+    }
+
+    /// Render the marker for a repo with no findings.
+    pub fn clean(&self) -> String {
+        self.paint(Paint::Green, "clean")
+    }
+
+    // Wrap text in an SGR color sequence, if enabled.
+    fn paint(&self, paint: Paint, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{text}\x1b[0m", paint.code())
+        } else {
+            text.to_owned()
+        }
+    }
+
+    // Wrap text in an OSC 8 hyperlink, if enabled.
+    fn link(&self, text: &str, url: &str) -> String {
+        if self.hyperlinks {
+            format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\")
+        } else {
+            text.to_owned()
+        }
+    }
+}
+
+// There's no way to ask a terminal whether it supports OSC 8, so go by what it says it is.
+fn terminal_supports_hyperlinks() -> bool {
+    let var = |name| env::var(name).unwrap_or_default();
+
+    if var("TERM") == "dumb" {
+        return false;
+    }
+    if ["WT_SESSION", "VTE_VERSION", "KONSOLE_VERSION", "DOMTERM"]
+        .iter()
+        .any(|name| env::var_os(name).is_some())
+    {
+        return true;
+    }
+    matches!(
+        var("TERM_PROGRAM").as_str(),
+        "iTerm.app" | "WezTerm" | "vscode" | "Hyper" | "ghostty"
+    ) || matches!(var("TERM").as_str(), "xterm-kitty" | "alacritty")
+}
+
+// Convert an absolute path to a `file://` URL, percent-encoding anything that isn't safe.
+fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut url = String::from("file://");
+    if !path.starts_with('/') {
+        url.push('/'); // Windows drive letter
+    }
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~:".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    url
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn links() {
+        let message = Message::new()
+            .text("Branch ")
+            .reference("main", Some("https://example.com/main".into()))
+            .text(" is behind ")
+            .reference("origin/main", None);
+
+        let plain = Renderer {
+            hyperlinks: false,
+            color: false,
+            ascii: false,
+            timestamps: Timestamps::default(),
+        };
+        assert_eq!(plain.message(&message), "Branch main is behind origin/main");
+
+        let linked = Renderer {
+            hyperlinks: true,
+            color: false,
+            ascii: false,
+            timestamps: Timestamps::default(),
+        };
+        assert_eq!(
+            linked.message(&message),
+            "Branch \x1b]8;;https://example.com/main\x1b\\main\x1b]8;;\x1b\\ is behind origin/main"
+        );
+    }
+
+    #[test]
+    fn colors() {
+        let renderer = Renderer {
+            hyperlinks: false,
+            color: true,
+            ascii: false,
+            timestamps: Timestamps::default(),
+        };
+        assert_eq!(
+            renderer.finding(&Finding::new(Check::Behind, "Branch main is behind")),
+            "\x1b[31mBranch main is behind\x1b[0m"
+        );
+        assert_eq!(
+            renderer.finding(&Finding::new(Check::Stashed, "Has 1 stashed changes")),
+            "\x1b[33mHas 1 stashed changes\x1b[0m"
+        );
+        assert_eq!(
+            renderer.finding(&Finding::new(Check::Merged, "Branch x is merged")),
+            "Branch x is merged"
+        );
+        assert_eq!(renderer.clean(), "\x1b[32mclean\x1b[0m");
+        assert_eq!(renderer.detail("-echo two"), "\x1b[31m-echo two\x1b[0m");
+        assert_eq!(renderer.detail("+echo 2"), "\x1b[32m+echo 2\x1b[0m");
+        assert_eq!(renderer.detail("+++ b/.git/hooks/x"), "+++ b/.git/hooks/x");
+        assert_eq!(renderer.detail(" echo one"), " echo one");
+
+        let mut escalated = Finding::new(Check::Merged, "Branch x is merged");
+        escalated.first_seen = Some(-30 * DAY);
+        escalated.escalate(14, 0);
+        assert_eq!(
+            renderer.finding(&escalated),
+            "\x1b[31mBranch x is merged, first seen 1969-12-02T00:00:00Z (4 weeks ago)\x1b[0m"
+        );
+
+        let plain = Renderer {
+            color: false,
+            ..renderer
+        };
+        assert_eq!(
+            plain.finding(&Finding::new(Check::Behind, "Branch main is behind")),
+            "Branch main is behind"
+        );
+    }
+
+    #[test]
+    fn ascii() {
+        let renderer = Renderer {
+            hyperlinks: false,
+            color: false,
+            ascii: true,
+            timestamps: Timestamps::default(),
+        };
+        assert_eq!(renderer.line("caf\u{e9} \x1b[1m"), "caf\\u{e9} \\u{1b}[1m");
+        assert_eq!(renderer.line("plain text"), "plain text");
+    }
+
+    #[test]
+    fn times() {
+        let now = 1_709_301_900; // 2024-03-01T14:05:00Z
+        let utc = Timestamps::new(TimeZone::default(), now);
+        assert_eq!(utc.iso(now), "2024-03-01T14:05:00Z");
+        assert_eq!(utc.format(now - 30), "2024-03-01T14:04:30Z (just now)");
+
+        let berlin = Timestamps::new(
+            TimeZone::from_rule("CET-1CEST,M3.5.0,M10.5.0/3").unwrap(),
+            now,
+        );
+        assert_eq!(berlin.iso(now), "2024-03-01T15:05:00+01:00");
+        assert_eq!(berlin.iso(now + 90 * DAY), "2024-05-30T16:05:00+02:00");
+        let new_york = Timestamps::new(TimeZone::from_rule("EST5EDT").unwrap(), now);
+        assert_eq!(new_york.iso(now), "2024-03-01T09:05:00-05:00");
+
+        for (ago, relative) in [
+            (60, "1 minute ago"),
+            (59 * 60, "59 minutes ago"),
+            (3 * 3600, "3 hours ago"),
+            (DAY, "1 day ago"),
+            (13 * DAY, "13 days ago"),
+            (21 * DAY, "3 weeks ago"),
+            (90 * DAY, "3 months ago"),
+            (800 * DAY, "2 years ago"),
+            (-2 * DAY, "in 2 days"),
+        ] {
+            assert_eq!(utc.relative(now - ago), relative, "{ago}");
+        }
+
+        let renderer = Renderer {
+            hyperlinks: false,
+            color: false,
+            ascii: false,
+            timestamps: utc,
+        };
+        let message = Message::new().text("Stashed ").time(now - 7 * DAY);
+        assert_eq!(
+            renderer.message(&message),
+            "Stashed 2024-02-23T14:05:00Z (7 days ago)"
+        );
+        assert_eq!(message.to_string(), "Stashed 2024-02-23T14:05:00Z");
+    }
+
+    #[test]
+    fn file_urls() {
+        assert_eq!(
+            file_url(Path::new("/home/me/my repos/kamino")),
+            "file:///home/me/my%20repos/kamino"
+        );
+    }
+}
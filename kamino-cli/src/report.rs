@@ -0,0 +1,1011 @@
+use crate::{
+    findings::{Check, Level, Message, Outcome, RepoReport, Segment},
+    json::Json,
+    lifecycle::Stage,
+    render::Timestamps,
+};
+use clap::ValueEnum;
+use kamino_core::Summary;
+use std::{
+    borrow::Cow,
+    fmt::Write,
+    fs, io,
+    io::Write as _,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+};
+
+/// Document formats a report can be rendered in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    Markdown,
+    Html,
+    /// One row per finding, for spreadsheets.
+    Csv,
+    /// One JSON object per line for each finding, for `jq` or log pipelines. Printed to stdout, each repo's findings
+    /// are written as soon as it has been checked.
+    Ndjson,
+    /// SARIF 2.1.0, for code scanning dashboards.
+    Sarif,
+    /// JUnit XML, with a test suite for each repo and a test case for each check, for CI test reports.
+    Junit,
+    /// GitHub Actions workflow commands, which annotate the run with each finding. Printed to stdout, each repo's
+    /// findings are written as soon as it has been checked, and the usual text is printed as well.
+    Github,
+}
+
+/// Command line options for writing a scan's results as a document.
+#[derive(clap::Args)]
+pub struct DocumentArgs {
+    /// Write the results as a standalone document in this format, instead of printing them as text
+    #[clap(long, value_enum, value_name = "FORMAT")]
+    pub format: Option<Format>,
+
+    /// Write the results to FILE (or stdout for -) as FORMAT: text, markdown, html, csv, ndjson, sarif, junit or
+    /// github. Without FORMAT=, the document is in the --format format. May be given multiple times, and the results
+    /// are still printed as text unless another output goes to stdout.
+    #[clap(long, value_name = "[FORMAT=]FILE")]
+    pub output: Vec<OutputSpec>,
+}
+
+/// What an output has in it: the usual text, or a document.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kind {
+    Text,
+    Document(Format),
+}
+
+/// An `--output` option: the format, if given, and the file to write to, or None for stdout.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OutputSpec {
+    pub kind: Option<Kind>,
+    pub path: Option<PathBuf>,
+}
+
+/// Parses `FORMAT=FILE` or just `FILE`, where `-` is stdout. Anything before an `=` that isn't a format is part of
+/// the file name.
+impl FromStr for OutputSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let kind = |name: &str| match name {
+            "text" => Some(Kind::Text),
+            _ => Format::from_str(name, true).ok().map(Kind::Document),
+        };
+        let (kind, path) = match s.split_once('=') {
+            Some((name, path)) if kind(name).is_some() => (kind(name), path),
+            _ => (None, s),
+        };
+        let path = match path {
+            "" => return Err(format!("no file in output \"{s}\" (use - for stdout)")),
+            "-" => None,
+            path => Some(PathBuf::from(path)),
+        };
+        Ok(Self { kind, path })
+    }
+}
+
+impl DocumentArgs {
+    /// Every output to write, with its format filled in. The text printed as usual isn't included, unless it was
+    /// asked for with `text=`.
+    ///
+    /// # Errors
+    ///
+    /// If an output has no format and there is no `--format`, or more than one output goes to stdout.
+    pub fn outputs(&self) -> Result<Vec<(Kind, Option<PathBuf>)>, String> {
+        let mut outputs = Vec::new();
+        for spec in &self.output {
+            let kind = match (spec.kind, self.format) {
+                (Some(kind), _) => kind,
+                (None, Some(format)) => Kind::Document(format),
+                (None, None) => {
+                    return Err(String::from(
+                        "--output needs a format, either as FORMAT=FILE or with --format",
+                    ))
+                }
+            };
+            outputs.push((kind, spec.path.clone()));
+        }
+        // Just --format prints the document instead of the text
+        if let (Some(format), true) = (self.format, self.output.is_empty()) {
+            outputs.push((Kind::Document(format), None));
+        }
+        if outputs.iter().filter(|(_, path)| path.is_none()).count() > 1 {
+            return Err(String::from("only one output can go to stdout"));
+        }
+        Ok(outputs)
+    }
+}
+
+impl Format {
+    /// Whether a document in this format is written a repo at a time when printed to stdout, by rendering each
+    /// repo's report on its own.
+    pub fn streamed(self) -> bool {
+        matches!(self, Self::Ndjson | Self::Github)
+    }
+
+    /// File extension for a document in this format.
+    #[cfg(feature = "share")]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Csv => "csv",
+            Self::Ndjson => "ndjson",
+            Self::Sarif => "sarif",
+            Self::Junit => "xml",
+            Self::Github => "txt",
+        }
+    }
+
+    /// MIME type for a document in this format.
+    #[cfg(feature = "share")]
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Markdown => "text/markdown; charset=utf-8",
+            Self::Html => "text/html; charset=utf-8",
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Ndjson => "application/x-ndjson",
+            Self::Sarif => "application/sarif+json",
+            Self::Junit => "application/xml; charset=utf-8",
+            Self::Github => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+/// Write a report to `path` so that a reader, e.g. a dashboard, never sees half of it: it goes into a temporary
+/// file in the same directory first, which then replaces `path` in one go. If writing fails, `path` is untouched.
+pub fn write(path: &Path, contents: &str) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file name"))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(format!(".{}.tmp", process::id()));
+    let temp = path.with_file_name(temp_name);
+    let result = fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Render the results of a scan as a standalone document. Only repos with findings or fixes are listed, except in
+/// JUnit, which has every repo; CSV, NDJSON, SARIF and JUnit only have findings. Times are shown as `timestamps` says,
+/// except that CSV, NDJSON, SARIF and JUnit only have the ISO 8601 time.
+pub fn render(
+    format: Format,
+    title: &str,
+    reports: &[RepoReport],
+    timestamps: &Timestamps,
+) -> String {
+    match format {
+        Format::Markdown => markdown(title, reports, timestamps),
+        Format::Html => html(title, reports, timestamps),
+        Format::Csv => csv(reports, timestamps),
+        Format::Ndjson => reports
+            .iter()
+            .map(|report| ndjson(report, timestamps))
+            .collect(),
+        Format::Sarif => sarif(reports, timestamps),
+        Format::Junit => junit(title, reports, timestamps),
+        Format::Github => reports
+            .iter()
+            .map(|report| github(report, timestamps))
+            .collect(),
+    }
+}
+
+// Describe the scan as a whole, e.g. "Scanned 12 repos, 2 need attention."
+fn summary(reports: &[RepoReport]) -> String {
+    let with_findings = reports.iter().filter(|r| !r.findings.is_empty()).count();
+    format!(
+        "Scanned {} repos, {with_findings} need attention.",
+        reports.len()
+    )
+}
+
+// Name of a repo as a heading, with its lifecycle stage if that is worth pointing out.
+fn heading(report: &RepoReport) -> String {
+    match report.stage {
+        Some(stage @ (Stage::Stale | Stage::Archived)) => format!("{} ({stage})", report.name),
+        _ => report.name.clone(),
+    }
+}
+
+// Repos worth listing, with the lines to list under each.
+fn listed(
+    reports: &[RepoReport],
+) -> impl Iterator<Item = (&RepoReport, Vec<(&str, Cow<'_, Message>)>)> {
+    reports.iter().filter_map(|report| {
+        let lines: Vec<(&str, Cow<'_, Message>)> = report
+            .findings
+            .iter()
+            .map(|finding| match finding.first_seen {
+                Some(_) => ("", Cow::Owned(finding.describe())),
+                None => ("", Cow::Borrowed(&finding.message)),
+            })
+            .chain(report.fixes.iter().map(|fix| match fix.outcome {
+                Outcome::Failed(_) => ("Fix failed: ", Cow::Owned(fix.describe())),
+                Outcome::Applied | Outcome::Planned => ("", Cow::Borrowed(&fix.message)),
+            }))
+            .collect();
+        (!lines.is_empty()).then_some((report, lines))
+    })
+}
+
+fn markdown(title: &str, reports: &[RepoReport], timestamps: &Timestamps) -> String {
+    let mut out = format!("# {}\n\n{}\n", markdown_escape(title), summary(reports));
+    for (report, lines) in listed(reports) {
+        let _ = write!(out, "\n## {}\n\n", markdown_escape(&heading(report)));
+        for (prefix, message) in lines {
+            out.push_str("- ");
+            out.push_str(prefix);
+            for segment in &message.0 {
+                match segment {
+                    Segment::Text(text) => out.push_str(&markdown_escape(text)),
+                    Segment::Ref {
+                        text,
+                        url: Some(url),
+                    } => {
+                        let _ = write!(out, "[`{text}`]({url})");
+                    }
+                    Segment::Ref { text, url: None } => {
+                        let _ = write!(out, "`{text}`");
+                    }
+                    Segment::Time(time) => out.push_str(&timestamps.format(*time)),
+                }
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+// Escape characters which Markdown would otherwise treat as formatting.
+fn markdown_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_[]<>#|".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// Kept small and inline so that the document stands alone, e.g. as an email attachment.
+const HTML_STYLE: &str = "body { font-family: sans-serif; max-width: 60em; margin: auto; }\n\
+                          table { border-collapse: collapse; }\n\
+                          th, td { text-align: left; padding: 0.2em 1em 0.2em 0; }\n\
+                          summary { font-size: 1.2em; font-weight: bold; cursor: pointer; margin-top: 0.5em; }\n";
+
+fn html(title: &str, reports: &[RepoReport], timestamps: &Timestamps) -> String {
+    let title = html_escape(title);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\n{HTML_STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n<p>{}</p>\n",
+        summary(reports)
+    );
+
+    let totals: Summary = reports.iter().map(|report| report.summary).sum();
+    out.push_str("<table>\n");
+    for (name, count) in [
+        ("Repos scanned", totals.repos),
+        ("Clean", totals.clean),
+        ("With uncommitted changes", totals.uncommitted),
+        ("Stashes", totals.stashes),
+        ("Branches ahead", totals.ahead),
+        ("Branches behind", totals.behind),
+        ("Hook problems", totals.hook_problems),
+    ] {
+        let _ = writeln!(out, "<tr><th>{name}</th><td>{count}</td></tr>");
+    }
+    if totals.at_risk > 0 {
+        let _ = writeln!(
+            out,
+            "<tr><th>Only on this machine</th><td>{}</td></tr>",
+            format_bytes(totals.at_risk)
+        );
+    }
+    out.push_str("</table>\n");
+
+    // Each repo is a collapsible section, open to begin with
+    for (report, lines) in listed(reports) {
+        let at_risk = match report.summary.at_risk {
+            0 => String::new(),
+            bytes => format!(" ({} only in this clone)", format_bytes(bytes)),
+        };
+        let _ = writeln!(
+            out,
+            "<details open>\n<summary>{}{at_risk}</summary>\n<ul>",
+            html_escape(&heading(report))
+        );
+        for (prefix, message) in lines {
+            let _ = write!(out, "<li>{prefix}");
+            for segment in &message.0 {
+                match segment {
+                    Segment::Text(text) => out.push_str(&html_escape(text)),
+                    Segment::Ref {
+                        text,
+                        url: Some(url),
+                    } => {
+                        let _ = write!(
+                            out,
+                            "<a href=\"{}\"><code>{}</code></a>",
+                            html_escape(url),
+                            html_escape(text)
+                        );
+                    }
+                    Segment::Ref { text, url: None } => {
+                        let _ = write!(out, "<code>{}</code>", html_escape(text));
+                    }
+                    Segment::Time(time) => {
+                        let _ = write!(
+                            out,
+                            "<time datetime=\"{}\">{}</time>",
+                            timestamps.iso(*time),
+                            timestamps.format(*time)
+                        );
+                    }
+                }
+            }
+            out.push_str("</li>\n");
+        }
+        out.push_str("</ul>\n</details>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+// A header row, then a row for each finding with the repo, the check, what the finding is about (the first
+// reference in the message: a branch, hook, tag and so on), the whole message and when it was first seen, if known.
+// Rows end with CRLF as in RFC 4180.
+fn csv(reports: &[RepoReport], timestamps: &Timestamps) -> String {
+    let mut out = String::from("repo,check,name,detail,first_seen\r\n");
+    for report in reports {
+        for finding in &report.findings {
+            let name = finding
+                .message
+                .0
+                .iter()
+                .find_map(|segment| match segment {
+                    Segment::Ref { text, .. } => Some(text.trim_matches('"')),
+                    Segment::Text(_) | Segment::Time(_) => None,
+                })
+                .unwrap_or_default();
+            let detail = plain(&finding.message, timestamps);
+            let first_seen = finding
+                .first_seen
+                .map(|time| timestamps.iso(time))
+                .unwrap_or_default();
+            let _ = write!(
+                out,
+                "{},{},{},{},{first_seen}\r\n",
+                csv_escape(&report.name),
+                finding.check,
+                csv_escape(name),
+                csv_escape(&detail)
+            );
+        }
+    }
+    out
+}
+
+// The whole of a message as plain text, with times as ISO 8601.
+fn plain(message: &Message, timestamps: &Timestamps) -> String {
+    message
+        .0
+        .iter()
+        .map(|segment| match segment {
+            Segment::Text(text) | Segment::Ref { text, .. } => Cow::Borrowed(text.as_str()),
+            Segment::Time(time) => Cow::Owned(timestamps.iso(*time)),
+        })
+        .collect()
+}
+
+/// A line of JSON for each of a repo's findings, with the repo's name and path, the check, level, message and when
+/// it was first seen, if known. Each line stands alone, so that a scan's results can be streamed a repo at a time.
+fn ndjson(report: &RepoReport, timestamps: &Timestamps) -> String {
+    let mut out = String::new();
+    for finding in &report.findings {
+        let json = Json::object([
+            ("repo", Json::from(report.name.as_str())),
+            ("path", Json::from(report.path.display().to_string())),
+            ("check", Json::from(finding.check.as_str())),
+            ("level", Json::from(finding.level.as_str())),
+            ("message", Json::from(plain(&finding.message, timestamps))),
+            (
+                "first_seen",
+                Json::from(finding.first_seen.map(|time| timestamps.iso(time))),
+            ),
+        ]);
+        let _ = writeln!(out, "{json}");
+    }
+    out
+}
+
+// A workflow command for each of a repo's findings, e.g. `::error file=/repos/a,title=a%3A ahead::Branch main is
+// ahead...`, which GitHub Actions shows as an annotation in the run's summary. Info findings are notices.
+fn github(report: &RepoReport, timestamps: &Timestamps) -> String {
+    let mut out = String::new();
+    for finding in &report.findings {
+        let command = match finding.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Info => "notice",
+        };
+        let _ = writeln!(
+            out,
+            "::{command} file={},title={}::{}",
+            github_escape(&report.path.display().to_string(), true),
+            github_escape(&format!("{}: {}", report.name, finding.check), true),
+            github_escape(&plain(&finding.message, timestamps), false)
+        );
+    }
+    out
+}
+
+// Escape what would otherwise end a workflow command's message, or in a property also what would end the property.
+fn github_escape(text: &str, property: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '%' => out.push_str("%25"),
+            '\r' => out.push_str("%0D"),
+            '\n' => out.push_str("%0A"),
+            ':' if property => out.push_str("%3A"),
+            ',' if property => out.push_str("%2C"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// A SARIF log with a rule for every check and a result for every finding, located at the repo's directory. The
+// finding's first reference, e.g. a branch or a file, is given as a logical location, and when it was first seen as
+// a property.
+fn sarif(reports: &[RepoReport], timestamps: &Timestamps) -> String {
+    let level = |level: Level| match level {
+        Level::Error => "error",
+        Level::Warning => "warning",
+        Level::Info => "note",
+    };
+    let rules: Vec<Json> = Check::ALL
+        .iter()
+        .map(|check| {
+            Json::object([
+                ("id", Json::from(check.as_str())),
+                (
+                    "shortDescription",
+                    Json::object([("text", Json::from(check.description()))]),
+                ),
+                (
+                    "defaultConfiguration",
+                    Json::object([("level", Json::from(level(check.level())))]),
+                ),
+            ])
+        })
+        .collect();
+
+    // Results are located at the repo's directory relative to the scanned directory, which is the base URI where all
+    // the repos are in the same absolute directory
+    let root = reports
+        .first()
+        .and_then(|report| report.path.parent())
+        .filter(|root| root.is_absolute())
+        .filter(|root| reports.iter().all(|r| r.path.parent() == Some(*root)));
+    let mut results = Vec::new();
+    for report in reports {
+        let directory = report
+            .path
+            .file_name()
+            .map_or(report.path.as_path(), Path::new);
+        for finding in &report.findings {
+            let rule_index = Check::ALL.iter().position(|check| *check == finding.check);
+            let mut location = vec![(
+                "physicalLocation",
+                Json::object([(
+                    "artifactLocation",
+                    Json::object([
+                        ("uri", Json::from(file_uri(directory) + "/")),
+                        ("uriBaseId", Json::from(SARIF_ROOT)),
+                    ]),
+                )]),
+            )];
+            let reference = finding.message.0.iter().find_map(|segment| match segment {
+                Segment::Ref { text, .. } => Some(text.trim_matches('"')),
+                Segment::Text(_) | Segment::Time(_) => None,
+            });
+            if let Some(name) = reference {
+                location.push((
+                    "logicalLocations",
+                    Json::Array(vec![Json::object([("name", Json::from(name))])]),
+                ));
+            }
+            let mut result = vec![
+                ("ruleId", Json::from(finding.check.as_str())),
+                ("ruleIndex", Json::from(rule_index)),
+                ("level", Json::from(level(finding.level))),
+                (
+                    "message",
+                    Json::object([("text", Json::from(plain(&finding.message, timestamps)))]),
+                ),
+                ("locations", Json::Array(vec![Json::object(location)])),
+            ];
+            if let Some(time) = finding.first_seen {
+                result.push((
+                    "properties",
+                    Json::object([("firstSeen", Json::from(timestamps.iso(time)))]),
+                ));
+            }
+            results.push(Json::object(result));
+        }
+    }
+
+    let driver = Json::object([
+        ("name", Json::from("kamino")),
+        ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+        (
+            "informationUri",
+            Json::from("https://github.com/schteve/kamino"),
+        ),
+        ("rules", Json::Array(rules)),
+    ]);
+    let mut run = vec![
+        ("tool", Json::object([("driver", driver)])),
+        ("results", Json::Array(results)),
+    ];
+    if let Some(root) = root {
+        let uri = Json::object([("uri", Json::from(file_uri(root) + "/"))]);
+        run.push(("originalUriBaseIds", Json::object([(SARIF_ROOT, uri)])));
+    }
+    let run = Json::object(run);
+    let log = Json::object([
+        ("version", Json::from("2.1.0")),
+        (
+            "$schema",
+            Json::from("https://json.schemastore.org/sarif-2.1.0.json"),
+        ),
+        ("runs", Json::Array(vec![run])),
+    ]);
+    format!("{log}\n")
+}
+
+// The base URI of the locations in a SARIF log: the scanned directory.
+const SARIF_ROOT: &str = "SCANROOT";
+
+// A path as a URI: `file://` for an absolute path, otherwise a relative reference, with `/` separators and anything
+// which isn't allowed in a URI percent-encoded.
+fn file_uri(path: &Path) -> String {
+    let text = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::new();
+    if text.starts_with('/') {
+        uri.push_str("file://");
+    } else if text.as_bytes().get(1) == Some(&b':') {
+        // A Windows drive letter
+        uri.push_str("file:///");
+    }
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(char::from(byte))
+            }
+            byte => {
+                let _ = write!(uri, "%{byte:02X}");
+            }
+        }
+    }
+    uri
+}
+
+// A test suite for each repo, with a test case for each check, which fails if the check found anything. The failure
+// has the first finding's message as its message, the most serious level as its type and every message in its body.
+fn junit(title: &str, reports: &[RepoReport], timestamps: &Timestamps) -> String {
+    let tests = reports.len() * Check::ALL.len();
+    let failed = |report: &RepoReport| {
+        Check::ALL
+            .iter()
+            .filter(|check| report.findings.iter().any(|f| f.check == **check))
+            .count()
+    };
+    let failures: usize = reports.iter().map(failed).sum();
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <testsuites name=\"{}\" tests=\"{tests}\" failures=\"{failures}\">\n",
+        html_escape(title)
+    );
+    for report in reports {
+        let name = html_escape(&report.name);
+        let _ = writeln!(
+            out,
+            "<testsuite name=\"{name}\" tests=\"{}\" failures=\"{}\">",
+            Check::ALL.len(),
+            failed(report)
+        );
+        let _ = writeln!(
+            out,
+            "<properties><property name=\"path\" value=\"{}\"/></properties>",
+            html_escape(&report.path.display().to_string())
+        );
+        for check in Check::ALL {
+            let findings: Vec<_> = report
+                .findings
+                .iter()
+                .filter(|finding| finding.check == check)
+                .collect();
+            let _ = write!(out, "<testcase name=\"{check}\" classname=\"{name}\"");
+            let (first, level) = match (
+                findings.first(),
+                findings.iter().map(|finding| finding.level).max(),
+            ) {
+                (Some(first), Some(level)) => (first, level),
+                _ => {
+                    out.push_str("/>\n");
+                    continue;
+                }
+            };
+            let _ = write!(
+                out,
+                ">\n<failure message=\"{}\" type=\"{level}\">",
+                html_escape(&plain(&first.message, timestamps))
+            );
+            for finding in &findings {
+                out.push_str(&html_escape(&plain(&finding.message, timestamps)));
+                if let Some(time) = finding.first_seen {
+                    let _ = write!(out, " (first seen {})", timestamps.iso(time));
+                }
+                out.push('\n');
+            }
+            out.push_str("</failure>\n</testcase>\n");
+        }
+        out.push_str("</testsuite>\n");
+    }
+    out.push_str("</testsuites>\n");
+    out
+}
+
+// Quote a field if it has anything which would otherwise end it, doubling any quotes inside.
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Describe a number of bytes, e.g. "1.3 GB".
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{bytes} bytes");
+    }
+    let mut value = bytes as f64 / 1000.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if value < 1000.0 {
+            break;
+        }
+        value /= 1000.0;
+        unit = next;
+    }
+    format!("{value:.1} {unit}")
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        changed::DAY,
+        findings::{Check, Finding, Fix, RemediationResult},
+        zone::TimeZone,
+    };
+
+    // 2024-03-01T14:05:00Z
+    const NOW: i64 = 1_709_301_900;
+
+    fn timestamps() -> Timestamps {
+        Timestamps::new(TimeZone::from_rule("CET-1").unwrap(), NOW)
+    }
+
+    fn reports() -> Vec<RepoReport> {
+        vec![
+            RepoReport {
+                summary: Summary {
+                    repos: 1,
+                    clean: 1,
+                    ..Summary::default()
+                },
+                ..RepoReport::for_test("clean", Vec::new())
+            },
+            RepoReport {
+                fixes: vec![
+                    RemediationResult::new(Fix::Hooks, false, "Copied hook <pre-commit>"),
+                    RemediationResult::failed(
+                        Fix::Push,
+                        Message::new().text("Push branch ").reference("main", None),
+                        "rejected",
+                    ),
+                ],
+                stage: Some(Stage::Stale),
+                summary: Summary {
+                    repos: 1,
+                    ahead: 1,
+                    at_risk: 1_300_000,
+                    ..Summary::default()
+                },
+                ..RepoReport::for_test(
+                    "my_repo",
+                    vec![Finding::new(
+                        Check::Ahead,
+                        Message::new()
+                            .text("Branch ")
+                            .reference("main", None)
+                            .text(" is ahead of ")
+                            .reference("origin/main", Some("https://example.com/main".into()))
+                            .text(" by 1 commits"),
+                    )],
+                )
+            },
+        ]
+    }
+
+    #[test]
+    fn write_atomic() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("report.html");
+        write(&path, "first").unwrap();
+        write(&path, "second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        // Nothing is left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // A failed write leaves the old report alone
+        assert!(write(&dir.path().join("missing/report.html"), "third").is_err());
+        assert!(write(dir.path(), "third").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn outputs() {
+        let args = |format: Option<Format>, output: &[&str]| DocumentArgs {
+            format,
+            output: output.iter().map(|s| s.parse().unwrap()).collect(),
+        };
+        let path = |p: &str| Some(PathBuf::from(p));
+
+        assert_eq!(args(None, &[]).outputs(), Ok(vec![]));
+        assert_eq!(
+            args(Some(Format::Html), &[]).outputs(),
+            Ok(vec![(Kind::Document(Format::Html), None)])
+        );
+        assert_eq!(
+            args(Some(Format::Html), &["report.html"]).outputs(),
+            Ok(vec![(Kind::Document(Format::Html), path("report.html"))])
+        );
+        assert_eq!(
+            args(None, &["text=-", "csv=a=b.csv", "HTML=report.html"]).outputs(),
+            Ok(vec![
+                (Kind::Text, None),
+                (Kind::Document(Format::Csv), path("a=b.csv")),
+                (Kind::Document(Format::Html), path("report.html")),
+            ])
+        );
+        assert_eq!(
+            args(None, &["x=y.md"]).outputs(),
+            Err("--output needs a format, either as FORMAT=FILE or with --format".into())
+        );
+        assert_eq!(
+            args(None, &["text=-", "markdown=-"]).outputs(),
+            Err("only one output can go to stdout".into())
+        );
+        assert!("csv=".parse::<OutputSpec>().is_err());
+    }
+
+    #[test]
+    fn markdown() {
+        let mut reports = reports();
+        let mut stashed = Finding::new(
+            Check::Stashed,
+            Message::new()
+                .text("Has 1 stashed changes, the oldest from ")
+                .time(NOW - 21 * DAY),
+        );
+        stashed.first_seen = Some(NOW - 14 * DAY);
+        reports[1].findings.push(stashed);
+        assert_eq!(
+            render(Format::Markdown, "Report for build-01", &reports, &timestamps()),
+            "# Report for build-01\n\nScanned 2 repos, 1 need attention.\n\n## my\\_repo (stale)\n\n\
+             - Branch `main` is ahead of [`origin/main`](https://example.com/main) by 1 commits\n\
+             - Has 1 stashed changes, the oldest from 2024-02-09T15:05:00+01:00 (3 weeks ago), first seen \
+             2024-02-16T15:05:00+01:00 (2 weeks ago)\n\
+             - Copied hook \\<pre-commit\\>\n\
+             - Fix failed: Push branch `main`: rejected\n"
+        );
+    }
+
+    #[test]
+    fn html() {
+        let mut reports = reports();
+        reports[1].findings.push(Finding::new(
+            Check::Stashed,
+            Message::new().text("Stashed at ").time(NOW - 7 * DAY),
+        ));
+        let html = render(Format::Html, "Report", &reports, &timestamps());
+        assert!(html.contains("<tr><th>Repos scanned</th><td>2</td></tr>"));
+        assert!(html.contains("<tr><th>Branches ahead</th><td>1</td></tr>"));
+        assert!(html.contains("<tr><th>Only on this machine</th><td>1.3 MB</td></tr>"));
+        assert!(html.contains(
+            "<details open>\n<summary>my_repo (stale) (1.3 MB only in this clone)</summary>"
+        ));
+        assert!(html.contains(
+            "<li>Branch <code>main</code> is ahead of \
+             <a href=\"https://example.com/main\"><code>origin/main</code></a> by 1 commits</li>"
+        ));
+        assert!(html.contains(
+            "<li>Stashed at <time datetime=\"2024-02-23T15:05:00+01:00\">\
+             2024-02-23T15:05:00+01:00 (7 days ago)</time></li>"
+        ));
+        assert!(html.contains("<li>Copied hook &lt;pre-commit&gt;</li>"));
+        assert!(!html.contains("<summary>clean"));
+    }
+
+    #[test]
+    fn csv() {
+        let mut reports = reports();
+        let mut hooks = Finding::new(
+            Check::Hooks,
+            Message::new()
+                .text("Hook ")
+                .reference("\"pre-commit\"", None)
+                .text(" is different in .git/hooks, and .githooks"),
+        );
+        hooks.first_seen = Some(NOW - DAY);
+        reports[1].findings.push(hooks);
+        reports[1].findings.push(Finding::new(
+            Check::Stashed,
+            Message::new().text("Stashed at ").time(NOW),
+        ));
+        assert_eq!(
+            render(Format::Csv, "Report", &reports, &timestamps()),
+            "repo,check,name,detail,first_seen\r\n\
+             my_repo,ahead,main,Branch main is ahead of origin/main by 1 commits,\r\n\
+             my_repo,hooks,pre-commit,\"Hook \"\"pre-commit\"\" is different in .git/hooks, and .githooks\",\
+             2024-02-29T15:05:00+01:00\r\n\
+             my_repo,stashed,,Stashed at 2024-03-01T15:05:00+01:00,\r\n"
+        );
+    }
+
+    #[test]
+    fn sarif() {
+        let mut reports = reports();
+        let mut stashed = Finding::new(Check::Stashed, "Has 1 stashed changes");
+        stashed.first_seen = Some(NOW - DAY);
+        reports[1].findings.push(stashed);
+        let sarif = render(Format::Sarif, "Report", &reports, &timestamps());
+        assert!(sarif.starts_with(
+            "{\"version\":\"2.1.0\",\"$schema\":\"https://json.schemastore.org/sarif-2.1.0.json\",\
+             \"runs\":[{\"tool\":{\"driver\":{\"name\":\"kamino\","
+        ));
+        assert!(sarif.contains(
+            "{\"id\":\"ahead\",\"shortDescription\":{\"text\":\"Local commits which aren't on the remote.\"},\
+             \"defaultConfiguration\":{\"level\":\"error\"}}"
+        ));
+        let ahead = Check::ALL.iter().position(|check| *check == Check::Ahead);
+        assert!(sarif.contains(&format!(
+            "\"results\":[{{\"ruleId\":\"ahead\",\"ruleIndex\":{},\"level\":\"error\",\
+             \"message\":{{\"text\":\"Branch main is ahead of origin/main by 1 commits\"}},\
+             \"locations\":[{{\"physicalLocation\":{{\"artifactLocation\":\
+             {{\"uri\":\"my_repo/\",\"uriBaseId\":\"SCANROOT\"}}}},\
+             \"logicalLocations\":[{{\"name\":\"main\"}}]}}]}},",
+            ahead.unwrap()
+        )));
+        assert!(sarif.contains(
+            "\"locations\":[{\"physicalLocation\":{\"artifactLocation\":\
+             {\"uri\":\"my_repo/\",\"uriBaseId\":\"SCANROOT\"}}}],\
+             \"properties\":{\"firstSeen\":\"2024-02-29T15:05:00+01:00\"}}],\
+             \"originalUriBaseIds\":{\"SCANROOT\":{\"uri\":\"file:///repos/\"}}}]}\n"
+        ));
+
+        // Repos which aren't in the same absolute directory are still relative to it, wherever it is
+        reports[1].path = "repos/my repo".into();
+        let sarif = render(Format::Sarif, "Report", &reports, &timestamps());
+        assert!(sarif.contains("{\"uri\":\"my%20repo/\",\"uriBaseId\":\"SCANROOT\"}"));
+        assert!(!sarif.contains("originalUriBaseIds"));
+
+        assert_eq!(file_uri(Path::new("repos/my repo")), "repos/my%20repo");
+        assert_eq!(
+            file_uri(Path::new("C:\\repos\\a#b")),
+            "file:///C:/repos/a%23b"
+        );
+    }
+
+    #[test]
+    fn junit() {
+        let mut reports = reports();
+        let mut stashed = Finding::new(Check::Stashed, "Stashed <wip>");
+        stashed.first_seen = Some(NOW - DAY);
+        reports[1].findings.push(stashed);
+        reports[1]
+            .findings
+            .push(Finding::new(Check::Stashed, "Stashed fix"));
+        let junit = render(Format::Junit, "Report", &reports, &timestamps());
+        let checks = Check::ALL.len();
+        assert!(junit.starts_with(&format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <testsuites name=\"Report\" tests=\"{}\" failures=\"2\">\n\
+             <testsuite name=\"clean\" tests=\"{checks}\" failures=\"0\">\n\
+             <properties><property name=\"path\" value=\"/repos/clean\"/></properties>\n\
+             <testcase name=\"uncommitted\" classname=\"clean\"/>\n",
+            2 * checks
+        )));
+        assert!(junit.contains(
+            "<testcase name=\"stashed\" classname=\"my_repo\">\n\
+             <failure message=\"Stashed &lt;wip&gt;\" type=\"warning\">\
+             Stashed &lt;wip&gt; (first seen 2024-02-29T15:05:00+01:00)\n\
+             Stashed fix\n\
+             </failure>\n</testcase>\n"
+        ));
+        assert!(junit.contains(
+            "<failure message=\"Branch main is ahead of origin/main by 1 commits\" type=\"error\">"
+        ));
+        assert!(junit.ends_with(&format!(
+            "<testcase name=\"{}\" classname=\"my_repo\"/>\n</testsuite>\n</testsuites>\n",
+            Check::ALL[checks - 1].as_str()
+        )));
+    }
+
+    #[test]
+    fn github() {
+        let mut reports = reports();
+        reports[1].findings.push(Finding::new(
+            Check::Stashed,
+            "Stash \"50%, then\nsome\" on main",
+        ));
+        reports[1]
+            .findings
+            .push(Finding::new(Check::PerfConfig, "Is slow"));
+        reports[1].path = "/repos/a,b:c".into();
+        let github = render(Format::Github, "Report", &reports, &timestamps());
+        assert_eq!(
+            github,
+            "::error file=/repos/a%2Cb%3Ac,title=my_repo%3A ahead::\
+             Branch main is ahead of origin/main by 1 commits\n\
+             ::warning file=/repos/a%2Cb%3Ac,title=my_repo%3A stashed::Stash \"50%25, then%0Asome\" on main\n\
+             ::notice file=/repos/a%2Cb%3Ac,title=my_repo%3A perf-config::Is slow\n"
+        );
+    }
+
+    #[test]
+    fn ndjson() {
+        let mut reports = reports();
+        let mut stashed = Finding::new(
+            Check::Stashed,
+            Message::new().text("Stashed \"wip\" at ").time(NOW),
+        );
+        stashed.first_seen = Some(NOW - DAY);
+        reports[1].findings.push(stashed);
+        assert_eq!(
+            render(Format::Ndjson, "Report", &reports, &timestamps()),
+            "{\"repo\":\"my_repo\",\"path\":\"/repos/my_repo\",\"check\":\"ahead\",\"level\":\"error\",\
+             \"message\":\"Branch main is ahead of origin/main by 1 commits\",\"first_seen\":null}\n\
+             {\"repo\":\"my_repo\",\"path\":\"/repos/my_repo\",\"check\":\"stashed\",\"level\":\"warning\",\
+             \"message\":\"Stashed \\\"wip\\\" at 2024-03-01T15:05:00+01:00\",\
+             \"first_seen\":\"2024-02-29T15:05:00+01:00\"}\n"
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use crate::output::Output;
+use anyhow::{bail, Context};
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use std::{
+    env, fmt, fs,
+    io::{Cursor, Read},
+    process::ExitCode,
+    str::FromStr,
+};
+
+/// Where the latest release is published.
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/schteve/kamino/releases/latest";
+
+/// A `major.minor.patch` version number.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Version(u64, u64, u64);
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid version \"{s}\"");
+        let mut parts = s.trim_start_matches('v').splitn(3, '.').map(|part| {
+            // Ignore any pre-release or build suffix
+            let digits = part.split(|c: char| !c.is_ascii_digit()).next();
+            digits.and_then(|d| d.parse().ok()).ok_or_else(err)
+        });
+        let mut next = || parts.next().unwrap_or_else(|| Err(err()));
+        Ok(Self(next()?, next()?, next()?))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// A downloadable file attached to a release.
+struct Asset {
+    name: String,
+    url: String,
+}
+
+/// The latest published release.
+struct Release {
+    version: Version,
+    page: String,
+    assets: Vec<Asset>,
+}
+
+fn get(url: &str) -> anyhow::Result<ureq::Response> {
+    ureq::get(url)
+        .set("User-Agent", concat!("kamino/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .with_context(|| format!("request to {url} failed"))
+}
+
+fn latest_release() -> anyhow::Result<Release> {
+    let json: serde_json::Value = get(LATEST_RELEASE_URL)?
+        .into_json()
+        .context("release feed is not valid JSON")?;
+
+    let tag = json["tag_name"]
+        .as_str()
+        .context("release feed has no tag name")?;
+    let version = tag.parse().map_err(anyhow::Error::msg)?;
+    let page = json["html_url"].as_str().unwrap_or_default().to_owned();
+    let assets = json["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|asset| {
+            Some(Asset {
+                name: asset["name"].as_str()?.to_owned(),
+                url: asset["browser_download_url"].as_str()?.to_owned(),
+            })
+        })
+        .collect();
+
+    Ok(Release {
+        version,
+        page,
+        assets,
+    })
+}
+
+fn download(url: &str) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    get(url)?
+        .into_reader()
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("failed to download {url}"))?;
+    Ok(bytes)
+}
+
+/// Take the executable out of a release archive, a `.zip` on Windows or a `.tar.gz` everywhere else.
+fn extract(archive_name: &str, bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let exe_name = format!("kamino{}", env::consts::EXE_SUFFIX);
+    let mut exe = Vec::new();
+    if archive_name.ends_with(".zip") {
+        let mut archive =
+            zip::ZipArchive::new(Cursor::new(bytes)).context("invalid zip archive")?;
+        archive
+            .by_name(&exe_name)
+            .with_context(|| format!("{archive_name} has no {exe_name}"))?
+            .read_to_end(&mut exe)?;
+    } else {
+        let mut archive = tar::Archive::new(GzDecoder::new(bytes));
+        let mut entry = archive
+            .entries()
+            .context("invalid tar.gz archive")?
+            .filter_map(Result::ok)
+            .find(|entry| {
+                let path = entry.path().ok();
+                path.and_then(|path| path.file_name().map(|name| name == exe_name.as_str()))
+                    == Some(true)
+            })
+            .with_context(|| format!("{archive_name} has no {exe_name}"))?;
+        entry.read_to_end(&mut exe)?;
+    }
+    Ok(exe)
+}
+
+/// Put `bytes` in place of the running executable. It's written next to it then swapped in, so a failure never
+/// leaves a partial binary. Windows won't replace a running executable but will rename it, so there the old one is
+/// moved aside first, to `kamino.old`.
+fn replace_exe(bytes: &[u8]) -> anyhow::Result<()> {
+    let exe = env::current_exe().context("failed to find the running executable")?;
+    let new_exe = exe.with_extension("new");
+    fs::write(&new_exe, bytes).with_context(|| format!("failed to write {}", new_exe.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&new_exe, fs::Permissions::from_mode(0o755))?;
+    }
+    if cfg!(windows) {
+        let old_exe = exe.with_extension("old");
+        // Left over from the last update, now that that kamino has exited
+        let _ = fs::remove_file(&old_exe);
+        fs::rename(&exe, &old_exe)
+            .with_context(|| format!("failed to move {} aside", exe.display()))?;
+        if let Err(e) = fs::rename(&new_exe, &exe) {
+            let _ = fs::rename(&old_exe, &exe);
+            return Err(e).with_context(|| format!("failed to replace {}", exe.display()));
+        }
+    } else {
+        fs::rename(&new_exe, &exe)
+            .with_context(|| format!("failed to replace {}", exe.display()))?;
+    }
+    Ok(())
+}
+
+/// Check for a newer release. Unless `check_only` is set, download the archive for this platform (if the release
+/// has one along with a `.sha256` checksum), verify it and replace the running executable with the one in it.
+pub fn run(check_only: bool, output: &mut Output) -> anyhow::Result<ExitCode> {
+    let current: Version = env!("CARGO_PKG_VERSION")
+        .parse()
+        .map_err(anyhow::Error::msg)?;
+    let release = latest_release()?;
+
+    if release.version <= current {
+        output.report(format!("kamino {current} is up to date"));
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    output.report(format!(
+        "kamino {} is available (installed: {current})",
+        release.version
+    ));
+
+    // Find the archive for this platform, as the release workflow names it, and its checksum
+    let archive = release.assets.iter().find(|asset| {
+        asset.name.contains(env::consts::OS)
+            && asset.name.contains(env::consts::ARCH)
+            && (asset.name.ends_with(".tar.gz") || asset.name.ends_with(".zip"))
+    });
+    let checksum = archive.and_then(|archive| {
+        let name = format!("{}.sha256", archive.name);
+        release.assets.iter().find(|asset| asset.name == name)
+    });
+
+    let (archive, checksum) = match (archive, checksum) {
+        (Some(archive), Some(checksum)) if !check_only => (archive, checksum),
+        _ => {
+            output.report("To upgrade, run `cargo install kamino-cli` or download it from:");
+            output.report(format!("    {}", release.page));
+            return Ok(ExitCode::SUCCESS);
+        }
+    };
+
+    output.report(format!("Downloading {}", archive.name));
+    let bytes = download(&archive.url)?;
+    let expected = String::from_utf8(download(&checksum.url)?)
+        .ok()
+        .and_then(|text| text.split_whitespace().next().map(str::to_lowercase))
+        .context("checksum file is not valid")?;
+    let actual = format!("{:x}", Sha256::digest(&bytes));
+    if actual != expected {
+        bail!(
+            "checksum mismatch for {}: expected {expected}, got {actual}",
+            archive.name
+        );
+    }
+
+    replace_exe(&extract(&archive.name, &bytes)?)?;
+
+    output.report(format!("Updated to kamino {}", release.version));
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    #[test]
+    fn version() {
+        assert_eq!("1.2.3".parse(), Ok(Version(1, 2, 3)));
+        assert_eq!("v1.10.0".parse(), Ok(Version(1, 10, 0)));
+        assert_eq!("v2.0.0-rc1".parse(), Ok(Version(2, 0, 0)));
+        assert!("v1.2".parse::<Version>().is_err());
+        assert!(Version(1, 10, 0) > Version(1, 9, 9));
+    }
+
+    #[test]
+    fn extract_exe() {
+        let exe_name = format!("kamino{}", env::consts::EXE_SUFFIX);
+        let contents = b"new kamino";
+
+        // Bundled as the release workflow does it, next to the docs
+        let mut tar = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (name, data) in [("README.md", &b"readme"[..]), (&exe_name, contents)] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            tar.append_data(&mut header, name, data).unwrap();
+        }
+        let tar_gz = tar.into_inner().unwrap().finish().unwrap();
+        assert_eq!(extract("kamino-v2.0.0.tar.gz", &tar_gz).unwrap(), contents);
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in [("README.md", &b"readme"[..]), (&exe_name, contents)] {
+            zip.start_file(name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(data).unwrap();
+        }
+        let zip = zip.finish().unwrap().into_inner();
+        assert_eq!(extract("kamino-v2.0.0.zip", &zip).unwrap(), contents);
+
+        assert!(extract("kamino-v2.0.0.zip", &tar_gz).is_err());
+        let empty = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        let empty = empty.into_inner().unwrap().finish().unwrap();
+        assert!(extract("kamino-v2.0.0.tar.gz", &empty).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{json::Json, report::Format};
+use anyhow::Context;
+use std::{env, fmt, str::FromStr};
+
+/// Where gists are created.
+const GISTS_URL: &str = "https://api.github.com/gists";
+
+/// Environment variable holding the GitHub token used to create gists.
+const GITHUB_TOKEN_VAR: &str = "GITHUB_TOKEN";
+
+/// Where to upload a shared report.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Destination {
+    /// A secret GitHub gist, created with the token in `GITHUB_TOKEN`.
+    Gist,
+    /// An HTTP(S) URL which accepts a PUT of the report.
+    Put(String),
+}
+
+impl FromStr for Destination {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "gist" {
+            Ok(Self::Gist)
+        } else if s.starts_with("https://") || s.starts_with("http://") {
+            Ok(Self::Put(s.to_owned()))
+        } else {
+            Err(format!(
+                "unknown destination \"{s}\" (expected \"gist\" or an http(s) URL)"
+            ))
+        }
+    }
+}
+
+impl fmt::Display for Destination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gist => f.write_str("gist"),
+            Self::Put(url) => f.write_str(url),
+        }
+    }
+}
+
+/// Upload a rendered report and return the URL where it can be viewed.
+pub fn upload(destination: &Destination, format: Format, document: &str) -> anyhow::Result<String> {
+    match destination {
+        Destination::Gist => upload_gist(format, document),
+        Destination::Put(url) => upload_put(url, format, document),
+    }
+}
+
+fn upload_gist(format: Format, document: &str) -> anyhow::Result<String> {
+    let token = env::var(GITHUB_TOKEN_VAR)
+        .with_context(|| format!("{GITHUB_TOKEN_VAR} must be set to share a report as a gist"))?;
+    let filename = format!("kamino-report.{}", format.extension());
+    let body = Json::object([
+        ("description", Json::from("kamino report")),
+        ("public", Json::from(false)),
+        (
+            "files",
+            Json::object([(filename, Json::object([("content", Json::from(document))]))]),
+        ),
+    ]);
+
+    let response: serde_json::Value = ureq::post(GISTS_URL)
+        .set("User-Agent", concat!("kamino/", env!("CARGO_PKG_VERSION")))
+        .set("Accept", "application/vnd.github+json")
+        .set("Authorization", &format!("Bearer {token}"))
+        .send_string(&body.to_string())
+        .context("failed to create gist")?
+        .into_json()
+        .context("gist response is not valid JSON")?;
+    response["html_url"]
+        .as_str()
+        .map(str::to_owned)
+        .context("gist response has no URL")
+}
+
+// Servers that store uploads often answer with where the upload can be found, either in `Location` or as the
+// body. Otherwise assume it can be read back from where it was put.
+fn upload_put(url: &str, format: Format, document: &str) -> anyhow::Result<String> {
+    let response = ureq::put(url)
+        .set("User-Agent", concat!("kamino/", env!("CARGO_PKG_VERSION")))
+        .set("Content-Type", format.content_type())
+        .send_string(document)
+        .with_context(|| format!("failed to upload report to {url}"))?;
+
+    if let Some(location) = response.header("Location") {
+        return Ok(location.to_owned());
+    }
+    let body = response.into_string().unwrap_or_default();
+    let body = body.trim();
+    if (body.starts_with("https://") || body.starts_with("http://"))
+        && !body.contains(char::is_whitespace)
+    {
+        Ok(body.to_owned())
+    } else {
+        Ok(url.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn destination() {
+        assert_eq!("gist".parse(), Ok(Destination::Gist));
+        assert_eq!(
+            "https://paste.example.com/build-01".parse(),
+            Ok(Destination::Put(
+                "https://paste.example.com/build-01".into()
+            ))
+        );
+        assert!("ftp://example.com".parse::<Destination>().is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::findings::RepoReport;
+use std::{fmt, str::FromStr, time::Duration};
+
+/// Time between scans in `kamino watch`, such as `30m`, `1h30m` or `45s`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Interval(pub Duration);
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || format!("bad interval \"{s}\" (expected e.g. 30m, 1h30m or 45s)");
+        let mut seconds = 0;
+        let mut number = String::new();
+        for c in s.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let unit = match c {
+                'h' => 60 * 60,
+                'm' => 60,
+                's' => 1,
+                _ => return Err(bad()),
+            };
+            let count: u64 = number.parse().map_err(|_| bad())?;
+            seconds += count * unit;
+            number.clear();
+        }
+        if !number.is_empty() || seconds == 0 {
+            return Err(bad());
+        }
+        Ok(Self(Duration::from_secs(seconds)))
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.0.as_secs();
+        let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+        if hours > 0 {
+            write!(f, "{hours}h")?;
+        }
+        if minutes > 0 {
+            write!(f, "{minutes}m")?;
+        }
+        if seconds > 0 {
+            write!(f, "{seconds}s")?;
+        }
+        Ok(())
+    }
+}
+
+/// The repos in `current` whose findings are different from the last scan, including repos which weren't in it
+/// and have findings. Repos with fixes always count as changed, since something was done to them.
+pub fn changed<'a>(previous: &[RepoReport], current: &'a [RepoReport]) -> Vec<&'a RepoReport> {
+    current
+        .iter()
+        .filter(|report| {
+            let before = previous.iter().find(|before| before.path == report.path);
+            let findings_before = before.map_or(&[][..], |before| &before.findings);
+            report.findings != findings_before || !report.fixes.is_empty()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::findings::{Check, Finding};
+
+    #[test]
+    fn intervals() {
+        let interval = |s: &str| s.parse::<Interval>().map(|i| i.0.as_secs());
+        assert_eq!(interval("30m"), Ok(30 * 60));
+        assert_eq!(interval("1h30m"), Ok(90 * 60));
+        assert_eq!(interval("45s"), Ok(45));
+        for bad in ["", "30", "0m", "m", "1d", "-5m"] {
+            assert!(interval(bad).is_err(), "{bad}");
+        }
+        assert_eq!("90m".parse::<Interval>().unwrap().to_string(), "1h30m");
+    }
+
+    #[test]
+    fn changes() {
+        let behind = || vec![Finding::new(Check::Behind, "Branch main is behind")];
+        let previous = vec![
+            RepoReport::for_test("same", behind()),
+            RepoReport::for_test("fixed", behind()),
+            RepoReport::for_test("broken", Vec::new()),
+        ];
+        let current = vec![
+            RepoReport::for_test("same", behind()),
+            RepoReport::for_test("fixed", Vec::new()),
+            RepoReport::for_test("broken", behind()),
+            RepoReport::for_test("new-clean", Vec::new()),
+            RepoReport::for_test("new-behind", behind()),
+        ];
+        let names: Vec<&str> = changed(&previous, &current)
+            .into_iter()
+            .map(|report| report.name.as_str())
+            .collect();
+        assert_eq!(names, ["fixed", "broken", "new-behind"]);
+    }
+}
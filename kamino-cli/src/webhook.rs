@@ -0,0 +1,137 @@
+use crate::{findings::RepoReport, json::Json, render::Timestamps};
+use anyhow::Context;
+use std::fmt::Write;
+
+/// Build the JSON payload describing a scan's findings. `text` is a plain summary, which is all that Slack-compatible
+/// incoming webhooks show. Other services can use `repos`, which has the findings of each repo that has any, with
+/// when each was first seen in ISO 8601.
+pub fn payload(title: &str, reports: &[RepoReport], timestamps: &Timestamps) -> Json {
+    let with_findings: Vec<&RepoReport> = reports
+        .iter()
+        .filter(|report| !report.findings.is_empty())
+        .collect();
+
+    let mut text = format!(
+        "{title}: {} of {} repos need attention",
+        with_findings.len(),
+        reports.len()
+    );
+    for report in &with_findings {
+        let _ = write!(text, "\n{}:", report.name);
+        for finding in &report.findings {
+            let _ = write!(text, "\n    {}", finding.describe());
+        }
+    }
+
+    let repos: Vec<Json> = with_findings
+        .iter()
+        .map(|report| {
+            let findings: Vec<Json> = report
+                .findings
+                .iter()
+                .map(|finding| {
+                    Json::object([
+                        ("check", Json::from(finding.check.as_str())),
+                        ("level", Json::from(finding.level.as_str())),
+                        ("message", Json::from(finding.message.to_string())),
+                        (
+                            "first_seen",
+                            Json::from(finding.first_seen.map(|time| timestamps.iso(time))),
+                        ),
+                    ])
+                })
+                .collect();
+            let fetches: Vec<Json> = report
+                .fetches
+                .iter()
+                .map(|fetch| {
+                    Json::object([
+                        ("remote", Json::from(fetch.remote.as_str())),
+                        ("received_bytes", Json::from(fetch.received_bytes)),
+                        ("received_objects", Json::from(fetch.received_objects)),
+                        ("duration_ms", Json::from(fetch.duration.as_millis() as i64)),
+                    ])
+                })
+                .collect();
+            Json::object([
+                ("name", Json::from(report.name.as_str())),
+                ("path", Json::from(report.path.display().to_string())),
+                ("findings", Json::Array(findings)),
+                ("fetches", Json::Array(fetches)),
+            ])
+        })
+        .collect();
+
+    Json::object([
+        ("text", Json::from(text)),
+        ("repos_scanned", Json::from(reports.len())),
+        ("repos", Json::Array(repos)),
+    ])
+}
+
+/// POST the scan's findings to a webhook URL, if there are any. Returns whether anything was sent.
+pub fn notify(
+    url: &str,
+    title: &str,
+    reports: &[RepoReport],
+    timestamps: &Timestamps,
+) -> anyhow::Result<bool> {
+    if reports.iter().all(|report| report.findings.is_empty()) {
+        return Ok(false);
+    }
+    ureq::post(url)
+        .set("User-Agent", concat!("kamino/", env!("CARGO_PKG_VERSION")))
+        .set("Content-Type", "application/json")
+        .send_string(&payload(title, reports, timestamps).to_string())
+        .with_context(|| format!("failed to notify webhook {url}"))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        findings::{Check, Finding},
+        zone::TimeZone,
+    };
+    use kamino_core::FetchStats;
+    use std::time::Duration;
+
+    #[test]
+    fn payloads() {
+        let mut reports = vec![
+            RepoReport::for_test("clean", Vec::new()),
+            RepoReport::for_test(
+                "dirty",
+                vec![Finding::new(Check::Ahead, "Branch main is ahead")],
+            ),
+        ];
+        reports[1].findings[0].first_seen = Some(1_709_301_900);
+        reports[1].fetches.push(FetchStats {
+            remote: "origin".into(),
+            received_bytes: 2048,
+            received_objects: 12,
+            duration: Duration::from_millis(1500),
+        });
+
+        let timestamps = Timestamps::new(TimeZone::default(), 1_709_301_900);
+        assert_eq!(
+            payload("Kamino report", &reports, &timestamps).to_string(),
+            "{\"text\":\"Kamino report: 1 of 2 repos need attention\\ndirty:\\n    \
+             Branch main is ahead, first seen 2024-03-01T14:05:00Z\",\
+             \"repos_scanned\":2,\
+             \"repos\":[{\"name\":\"dirty\",\"path\":\"/repos/dirty\",\
+             \"findings\":[{\"check\":\"ahead\",\"level\":\"error\",\"message\":\"Branch main is ahead\",\
+             \"first_seen\":\"2024-03-01T14:05:00Z\"}],\
+             \"fetches\":[{\"remote\":\"origin\",\"received_bytes\":2048,\"received_objects\":12,\
+             \"duration_ms\":1500}]}]}"
+        );
+        let sent = notify(
+            "http://localhost:1",
+            "Kamino report",
+            &reports[..1],
+            &timestamps,
+        );
+        assert!(!sent.unwrap());
+    }
+}
@@ -0,0 +1,400 @@
+use crate::changed::{civil_from_days, days_from_civil, DAY};
+use std::{env, fs, path::Path};
+
+/// Where the system's time zone is, if `TZ` doesn't say.
+const LOCALTIME: &str = "/etc/localtime";
+
+/// Where time zone files are looked for by name, e.g. for `TZ=Europe/Berlin`.
+const ZONEINFO: &str = "/usr/share/zoneinfo";
+
+/// A time zone: the offset from UTC at any time, from a TZif file in the system's time zone database or a POSIX `TZ`
+/// string such as `CET-1CEST,M3.5.0,M10.5.0/3`. The default is UTC.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TimeZone {
+    /// Times when the offset changed, in seconds since the Unix epoch, with the offset from then on.
+    transitions: Vec<(i64, i32)>,
+    /// The offset before the first transition.
+    initial: i32,
+    /// The offset after the last transition.
+    rule: Option<Rule>,
+}
+
+impl TimeZone {
+    /// The local time zone: from `TZ` if it is set, otherwise from `/etc/localtime`. Falls back to UTC if neither
+    /// can be read.
+    pub fn local() -> Self {
+        let tz = match env::var("TZ") {
+            Ok(tz) if !tz.is_empty() => tz,
+            _ => return Self::from_file(Path::new(LOCALTIME)).unwrap_or_default(),
+        };
+        // `:name` and `name` are both a file, relative to the database unless absolute
+        let name = tz.strip_prefix(':').unwrap_or(&tz);
+        let path = Path::new(ZONEINFO).join(name);
+        Self::from_file(&path)
+            .or_else(|| Self::from_rule(&tz))
+            .unwrap_or_default()
+    }
+
+    fn from_file(path: &Path) -> Option<Self> {
+        Self::from_tzif(&fs::read(path).ok()?)
+    }
+
+    /// A time zone from a POSIX `TZ` string, e.g. `EST5EDT,M3.2.0,M11.1.0`.
+    pub fn from_rule(rule: &str) -> Option<Self> {
+        let rule = Rule::parse(rule)?;
+        Some(Self {
+            transitions: Vec::new(),
+            initial: rule.std_offset,
+            rule: Some(rule),
+        })
+    }
+
+    /// Read a TZif file, as in `/usr/share/zoneinfo`.
+    pub fn from_tzif(data: &[u8]) -> Option<Self> {
+        let header = Header::parse(data)?;
+        // Version 2 and later repeat the data with 64-bit times, then have a TZ string for later times
+        let (header, body, time_size) = if header.version >= b'2' {
+            let rest = data.get(44 + header.v1_len()..)?;
+            (Header::parse(rest)?, rest, 8)
+        } else {
+            (header, data, 4)
+        };
+        let mut at = 44;
+        let mut take = |len: usize| -> Option<&[u8]> {
+            let bytes = body.get(at..at + len)?;
+            at += len;
+            Some(bytes)
+        };
+
+        let times: Vec<i64> = take(header.timecnt * time_size)?
+            .chunks(time_size)
+            .map(|chunk| match *chunk {
+                [a, b, c, d] => i64::from(i32::from_be_bytes([a, b, c, d])),
+                _ => i64::from_be_bytes(chunk.try_into().unwrap_or_default()),
+            })
+            .collect();
+        let indices = take(header.timecnt)?.to_vec();
+        let offsets: Vec<i32> = take(header.typecnt * 6)?
+            .chunks(6)
+            .map(|ttinfo| i32::from_be_bytes([ttinfo[0], ttinfo[1], ttinfo[2], ttinfo[3]]))
+            .collect();
+        let mut transitions = Vec::with_capacity(times.len());
+        for (time, index) in times.into_iter().zip(indices) {
+            transitions.push((time, *offsets.get(usize::from(index))?));
+        }
+
+        let rule = if time_size == 8 {
+            take(header.charcnt + header.leapcnt * 12 + header.isstdcnt + header.isutcnt)?;
+            let footer = String::from_utf8_lossy(body.get(at..)?).into_owned();
+            Rule::parse(footer.trim_matches('\n'))
+        } else {
+            None
+        };
+        Some(Self {
+            transitions,
+            initial: offsets.first().copied().unwrap_or(0),
+            rule,
+        })
+    }
+
+    /// The offset from UTC at a time, in seconds.
+    pub fn offset(&self, time: i64) -> i32 {
+        match self
+            .transitions
+            .iter()
+            .rposition(|(start, _)| *start <= time)
+        {
+            Some(i) if i + 1 < self.transitions.len() || self.rule.is_none() => {
+                self.transitions[i].1
+            }
+            None if !self.transitions.is_empty() => self.initial,
+            _ => match &self.rule {
+                Some(rule) => rule.offset(time),
+                None => self.initial,
+            },
+        }
+    }
+}
+
+// The counts in a TZif header.
+struct Header {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.get(..4)? != b"TZif" {
+            return None;
+        }
+        let count = |i: usize| -> Option<usize> {
+            let bytes = data.get(20 + i * 4..24 + i * 4)?;
+            usize::try_from(u32::from_be_bytes(bytes.try_into().ok()?)).ok()
+        };
+        Some(Self {
+            version: *data.get(4)?,
+            isutcnt: count(0)?,
+            isstdcnt: count(1)?,
+            leapcnt: count(2)?,
+            timecnt: count(3)?,
+            typecnt: count(4)?,
+            charcnt: count(5)?,
+        })
+    }
+
+    // Length of the data after a version 1 header.
+    fn v1_len(&self) -> usize {
+        self.timecnt * 5
+            + self.typecnt * 6
+            + self.charcnt
+            + self.leapcnt * 8
+            + self.isstdcnt
+            + self.isutcnt
+    }
+}
+
+/// The offsets given by a POSIX `TZ` string: standard time, and daylight saving time between two days of each year.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Rule {
+    std_offset: i32,
+    dst: Option<Dst>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Dst {
+    offset: i32,
+    start: (Day, i32),
+    end: (Day, i32),
+}
+
+/// A day of the year in a POSIX `TZ` string.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Day {
+    /// `Jn`: 1 to 365, never counting February 29.
+    Julian(i64),
+    /// `n`: 0 to 365, counting February 29.
+    Ordinal(i64),
+    /// `Mm.w.d`: weekday `d` (0 is Sunday) of week `w` (5 is the last) of month `m`.
+    Weekday { month: i64, week: i64, weekday: i64 },
+}
+
+impl Rule {
+    fn parse(s: &str) -> Option<Self> {
+        let mut rest = s;
+        name(&mut rest)?;
+        // POSIX offsets are west of UTC, so have the opposite sign to the usual ones
+        let std_offset = -duration(&mut rest)?;
+        if rest.is_empty() {
+            return Some(Self {
+                std_offset,
+                dst: None,
+            });
+        }
+        name(&mut rest)?;
+        let offset = match rest.chars().next() {
+            Some(',') | None => std_offset + 3600,
+            Some(_) => -duration(&mut rest)?,
+        };
+        // Without dates, US rules are assumed, as glibc does
+        let rules = match rest.strip_prefix(',') {
+            Some(rules) => rules,
+            None if rest.is_empty() => "M3.2.0,M11.1.0",
+            None => return None,
+        };
+        let (start, end) = rules.split_once(',')?;
+        Some(Self {
+            std_offset,
+            dst: Some(Dst {
+                offset,
+                start: transition(start)?,
+                end: transition(end)?,
+            }),
+        })
+    }
+
+    fn offset(&self, time: i64) -> i32 {
+        let dst = match &self.dst {
+            Some(dst) => dst,
+            None => return self.std_offset,
+        };
+        let (year, _, _) = civil_from_days((time + i64::from(self.std_offset)).div_euclid(DAY));
+        // Transition times are in the local time in effect before them
+        let start = dst.start.0.date(year) * DAY + i64::from(dst.start.1 - self.std_offset);
+        let end = dst.end.0.date(year) * DAY + i64::from(dst.end.1 - dst.offset);
+        let in_dst = if start < end {
+            start <= time && time < end
+        } else {
+            // Southern hemisphere, where DST spans the new year
+            !(end <= time && time < start)
+        };
+        if in_dst {
+            dst.offset
+        } else {
+            self.std_offset
+        }
+    }
+}
+
+impl Day {
+    // Days since the Unix epoch of this day in a year.
+    fn date(self, year: i64) -> i64 {
+        let jan1 = days_from_civil(year, 1, 1);
+        let leap = days_from_civil(year, 3, 1) - days_from_civil(year, 2, 1) == 29;
+        match self {
+            Self::Julian(n) => jan1 + n - 1 + i64::from(leap && n >= 60),
+            Self::Ordinal(n) => jan1 + n,
+            Self::Weekday {
+                month,
+                week,
+                weekday,
+            } => {
+                let first = days_from_civil(year, month, 1);
+                let next = if month == 12 {
+                    days_from_civil(year + 1, 1, 1)
+                } else {
+                    days_from_civil(year, month + 1, 1)
+                };
+                // 1970-01-01 was a Thursday
+                let first_weekday = (first + 4).rem_euclid(7);
+                let mut day = first + (weekday - first_weekday).rem_euclid(7) + (week - 1) * 7;
+                while day >= next {
+                    day -= 7;
+                }
+                day
+            }
+        }
+    }
+}
+
+// Skip a time zone abbreviation: letters, or anything in angle brackets.
+fn name(rest: &mut &str) -> Option<()> {
+    let len = if let Some(quoted) = rest.strip_prefix('<') {
+        quoted.find('>')? + 2
+    } else {
+        rest.find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len())
+    };
+    if len < 3 {
+        return None;
+    }
+    *rest = &rest[len..];
+    Some(())
+}
+
+// Parse `[+-]hh[:mm[:ss]]` as seconds.
+fn duration(rest: &mut &str) -> Option<i32> {
+    let len = rest
+        .find(|c: char| !(c.is_ascii_digit() || "+-:".contains(c)))
+        .unwrap_or(rest.len());
+    let (text, after) = rest.split_at(len);
+    *rest = after;
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(text) => (-1, text),
+        None => (1, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let mut seconds = 0;
+    for (part, scale) in text.split(':').zip([3600, 60, 1]) {
+        seconds += part.parse::<i32>().ok()? * scale;
+    }
+    Some(sign * seconds)
+}
+
+// Parse a day and optional time of a DST transition, e.g. `M3.5.0/3`. The time defaults to 02:00.
+fn transition(s: &str) -> Option<(Day, i32)> {
+    let (day, time) = match s.split_once('/') {
+        Some((day, mut time)) => (day, duration(&mut time)?),
+        None => (s, 2 * 3600),
+    };
+    let day = if let Some(n) = day.strip_prefix('J') {
+        Day::Julian(n.parse().ok().filter(|n| (1..=365).contains(n))?)
+    } else if let Some(date) = day.strip_prefix('M') {
+        let mut parts = date.split('.').map(str::parse::<i64>);
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(month)), Some(Ok(week)), Some(Ok(weekday)), None)
+                if (1..=12).contains(&month)
+                    && (1..=5).contains(&week)
+                    && (0..=6).contains(&weekday) =>
+            {
+                Day::Weekday {
+                    month,
+                    week,
+                    weekday,
+                }
+            }
+            _ => return None,
+        }
+    } else {
+        Day::Ordinal(day.parse().ok().filter(|n| (0..=365).contains(n))?)
+    };
+    Some((day, time))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Seconds since the Unix epoch of a UTC date and time.
+    fn utc(year: i64, month: i64, day: i64, hour: i64) -> i64 {
+        days_from_civil(year, month, day) * DAY + hour * 3600
+    }
+
+    #[test]
+    fn rules() {
+        assert_eq!(TimeZone::default().offset(utc(2024, 7, 1, 0)), 0);
+        assert_eq!(
+            TimeZone::from_rule("<+0530>-5:30").unwrap().offset(0),
+            5 * 3600 + 1800
+        );
+
+        // Central Europe: summer time from 01:00 UTC on the last Sunday in March to 01:00 UTC on the last Sunday in
+        // October, which in 2024 are the 31st and the 27th
+        let berlin = TimeZone::from_rule("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert_eq!(berlin.offset(utc(2024, 1, 15, 12)), 3600);
+        assert_eq!(berlin.offset(utc(2024, 3, 31, 0)), 3600);
+        assert_eq!(berlin.offset(utc(2024, 3, 31, 1)), 7200);
+        assert_eq!(berlin.offset(utc(2024, 10, 27, 0)), 7200);
+        assert_eq!(berlin.offset(utc(2024, 10, 27, 1)), 3600);
+
+        // US Eastern, with the default dates
+        let new_york = TimeZone::from_rule("EST5EDT").unwrap();
+        assert_eq!(new_york.offset(utc(2024, 1, 1, 0)), -5 * 3600);
+        assert_eq!(new_york.offset(utc(2024, 3, 10, 6)), -5 * 3600);
+        assert_eq!(new_york.offset(utc(2024, 3, 10, 7)), -4 * 3600);
+
+        // Southern hemisphere, where summer time spans the new year
+        let sydney = TimeZone::from_rule("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(sydney.offset(utc(2024, 1, 1, 0)), 11 * 3600);
+        assert_eq!(sydney.offset(utc(2024, 7, 1, 0)), 10 * 3600);
+
+        for bad in ["", "X1", "CET", "CET-1CEST,M13.1.0,M10.5.0", "CET-1CEST,x"] {
+            assert_eq!(TimeZone::from_rule(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn tzif() {
+        // A version 1 file with one transition, from +01:00 to +02:00 at 1000
+        let mut data = b"TZif".to_vec();
+        data.extend([0; 16]);
+        for count in [0_u32, 0, 0, 1, 2, 8] {
+            data.extend(count.to_be_bytes());
+        }
+        data.extend(1000_i32.to_be_bytes());
+        data.push(1);
+        data.extend(3600_i32.to_be_bytes());
+        data.extend([0, 0]);
+        data.extend(7200_i32.to_be_bytes());
+        data.extend([1, 4]);
+        data.extend(b"ABC\0DEF\0");
+
+        let zone = TimeZone::from_tzif(&data).unwrap();
+        assert_eq!(zone.offset(999), 3600);
+        assert_eq!(zone.offset(1000), 7200);
+        assert_eq!(TimeZone::from_tzif(b"nope"), None);
+    }
+}
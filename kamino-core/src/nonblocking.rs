@@ -0,0 +1,141 @@
+//! Async versions of the checks, for embedders running on a tokio runtime, such as a GUI or a service, which mustn't
+//! block it on network I/O. Each opens the repo and runs the check on tokio's blocking thread pool, since libgit2
+//! has no async API of its own.
+//!
+//! ```no_run
+//! use kamino_core::{nonblocking, ScanOptions};
+//!
+//! # async fn scan() -> Result<(), Box<dyn std::error::Error>> {
+//! let fetched = nonblocking::check_ahead_behind("path/to/repo", ScanOptions::new()).await?;
+//! let uncommitted = nonblocking::with_repo("path/to/repo", |repo| kamino_core::check_uncommitted(repo)).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{AheadBehindError, AheadBehindFetched, FetchProgress, ScanOptions};
+use git2::Repository;
+use std::{panic, path::PathBuf};
+
+/// Error type for the async checks, where `E` is the error of the check itself.
+#[derive(thiserror::Error, Debug)]
+pub enum Error<E> {
+    /// Failed to open the repo.
+    #[error("failed to open repo {path}")]
+    Open {
+        /// Path to the repo.
+        path: PathBuf,
+        /// Underlying error.
+        source: git2::Error,
+    },
+
+    /// The check failed.
+    #[error(transparent)]
+    Check(E),
+
+    /// The runtime shut down before the check finished.
+    #[error("the runtime shut down before the check finished")]
+    Cancelled,
+}
+
+/// Open the repo at `path` and run `check` with it on tokio's blocking thread pool, e.g. to run any of the checks
+/// without blocking the runtime. A panic in `check` is passed on to the caller.
+///
+/// # Errors
+///
+/// See [`Error`], where [`Error::Check`] has the error `check` returned.
+pub async fn with_repo<T, E, F>(path: impl Into<PathBuf>, check: F) -> Result<T, Error<E>>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+    F: FnOnce(&mut Repository) -> Result<T, E> + Send + 'static,
+{
+    let path = path.into();
+    let task = tokio::task::spawn_blocking(move || {
+        let mut repo = Repository::open(&path).map_err(|source| Error::Open { path, source })?;
+        check(&mut repo).map_err(Error::Check)
+    });
+    match task.await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+        Err(_) => Err(Error::Cancelled),
+    }
+}
+
+/// Like [`check_ahead_behind_with()`](crate::check_ahead_behind_with()), fetching as the options say, but without
+/// blocking the runtime while fetching.
+///
+/// # Errors
+///
+/// See [`Error`] and [`AheadBehindError`].
+pub async fn check_ahead_behind(
+    path: impl Into<PathBuf>,
+    options: ScanOptions,
+) -> Result<AheadBehindFetched, Error<AheadBehindError>> {
+    check_ahead_behind_progress(path, options, |_| {}).await
+}
+
+/// Like [`check_ahead_behind()`], but call `progress` as each fetch receives data. It is called from the thread
+/// doing the fetch, so e.g. send the progress to a channel to show it.
+///
+/// # Errors
+///
+/// See [`Error`] and [`AheadBehindError`].
+pub async fn check_ahead_behind_progress(
+    path: impl Into<PathBuf>,
+    options: ScanOptions,
+    mut progress: impl FnMut(&FetchProgress) + Send + 'static,
+) -> Result<AheadBehindFetched, Error<AheadBehindError>> {
+    with_repo(path, move |repo| {
+        crate::check_ahead_behind_progress(repo, &options, &mut progress)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use git2::Signature;
+    use std::path::Path;
+
+    fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn commit(repo: &Repository, message: &str) {
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap();
+    }
+
+    #[test]
+    fn ahead_behind() {
+        let upstream_dir = tempfile::TempDir::new().unwrap();
+        let upstream = Repository::init(upstream_dir.path()).unwrap();
+        commit(&upstream, "first");
+        let local_dir = tempfile::TempDir::new().unwrap();
+        Repository::clone(upstream_dir.path().to_str().unwrap(), local_dir.path()).unwrap();
+        commit(&upstream, "second");
+
+        let fetched = block_on(check_ahead_behind(local_dir.path(), ScanOptions::new())).unwrap();
+        let results: Vec<_> = fetched.results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(results.len(), 1);
+        assert_eq!((results[0].ahead, results[0].behind), (Some(0), Some(1)));
+        assert_eq!(fetched.fetches.len(), 1);
+
+        let head = block_on(with_repo(local_dir.path(), |repo| repo.head().map(|_| ())));
+        assert!(head.is_ok());
+        let missing = Path::new("/nonexistent/kamino/repo");
+        assert!(matches!(
+            block_on(with_repo(missing, |_| Ok::<_, git2::Error>(()))),
+            Err(Error::Open { .. })
+        ));
+    }
+}